@@ -1,5 +1,14 @@
+use std::{ffi::OsString, path::PathBuf};
+
 use clap::{Parser, Subcommand};
 
+use crate::{
+    export::ExportFormat,
+    pin::PinFile,
+    shell::Shell,
+    versions::{Arch, Os},
+};
+
 #[derive(Parser)]
 #[clap(name = "Yet Another Node Version Manager", about)]
 pub struct Args {
@@ -8,6 +17,13 @@ pub struct Args {
 
     #[clap(long, help = "Force running as admin")]
     pub admin: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "The architecture to install or use. Defaults to the configured or host architecture"
+    )]
+    pub arch: Option<Arch>,
 }
 
 #[derive(Debug, Subcommand, PartialEq, Eq)]
@@ -15,9 +31,235 @@ pub enum Commands {
     Install {
         #[clap(
             name = "VERSION",
-            help = "The version to install. Possible Formats: lts, latest, vXX.XX.XX",
+            help = "The versions to install. Defaults to the version pinned by the nearest .nvmrc, .node-version or .tool-versions file, or else lts. Possible Formats: lts, latest, vXX, vXX.XX, vXX.XX.XX, nightly, rc/XX, v8-canary, vXX.XX.XX-rc.X"
+        )]
+        version_strs: Vec<String>,
+
+        #[clap(
+            long,
+            default_value = "4",
+            help = "The number of versions to download and extract at once"
+        )]
+        parallel: usize,
+
+        #[clap(
+            long,
+            group = "archive",
+            help = "Install from a local NodeJS archive instead of downloading a release"
+        )]
+        from_archive: Option<PathBuf>,
+
+        #[clap(
+            long,
+            group = "archive",
+            help = "Install from a NodeJS archive at the given url"
+        )]
+        from_url: Option<String>,
+
+        #[clap(
+            long,
+            requires = "archive",
+            help = "The expected sha256 checksum of the archive given by --from-archive or --from-url"
+        )]
+        sha256: Option<String>,
+
+        #[clap(
+            long,
+            help = "Install even if the release is known to be incompatible with this system"
+        )]
+        force: bool,
+
+        #[clap(
+            long,
+            help = "Build the release from its source tarball instead of downloading a binary",
+            conflicts_with = "archive"
+        )]
+        from_source: bool,
+
+        #[clap(
+            short,
+            long,
+            requires = "from-source",
+            help = "The number of parallel jobs to build with. Defaults to `build_jobs` in the config, or else the number of CPUs"
+        )]
+        jobs: Option<usize>,
+
+        #[clap(
+            long = "configure-flag",
+            requires = "from-source",
+            multiple_occurrences = true,
+            allow_hyphen_values = true,
+            help = "A flag to pass to the configure script, after any `configure_flags` in the config. Can be given multiple times"
+        )]
+        configure_flags: Vec<String>,
+    },
+    #[clap(about = "Register an existing NodeJS installation under a custom name")]
+    Link {
+        #[clap(name = "NAME", help = "The name to register the installation as")]
+        name: String,
+
+        #[clap(
+            name = "PATH",
+            help = "The NodeJS installation prefix, containing a bin directory"
+        )]
+        path: PathBuf,
+    },
+    #[clap(about = "Switch the current version")]
+    Use {
+        #[clap(
+            name = "VERSION",
+            help = "The installed version or linked name to use. Defaults to the version pinned by the nearest .nvmrc, .node-version or .tool-versions file. Possible Formats: system, vXX, vXX.XX, vXX.XX.XX, nightly, rc/XX, vXX.XX.XX-rc.X, NAME"
+        )]
+        version_str: Option<String>,
+
+        #[clap(long, help = "Install the version if it is not installed yet")]
+        install: bool,
+
+        #[clap(
+            long,
+            help = "Only switch the version used by this shell, leaving other shells untouched"
+        )]
+        shell: bool,
+
+        // Used by the shell hook printed by `env --use-on-cd`
+        #[clap(long, hide = true)]
+        on_cd: bool,
+    },
+    #[clap(
+        about = "Run a command with a version on the PATH, without switching the current version"
+    )]
+    Exec {
+        #[clap(
+            name = "VERSION",
+            help = "The installed version or linked name to run the command with. Defaults to the version pinned by the nearest .nvmrc, .node-version or .tool-versions file. Possible Formats: system, vXX, vXX.XX, vXX.XX.XX, nightly, rc/XX, vXX.XX.XX-rc.X, NAME"
+        )]
+        version_str: Option<String>,
+
+        #[clap(long, help = "Install the version if it is not installed yet")]
+        install: bool,
+
+        #[clap(
+            name = "COMMAND",
+            required = true,
+            last = true,
+            parse(from_os_str),
+            help = "The command to run, and its arguments"
+        )]
+        command: Vec<OsString>,
+    },
+    #[clap(about = "Write a version to the project's version file")]
+    Pin {
+        #[clap(
+            name = "VERSION",
+            help = "The installed version or linked name to pin. Defaults to the current version. Possible Formats: vXX, vXX.XX, vXX.XX.XX, nightly, rc/XX, vXX.XX.XX-rc.X, NAME"
+        )]
+        version_str: Option<String>,
+
+        #[clap(
+            arg_enum,
+            long,
+            help = "The file to write in the working directory. Defaults to the project's existing version file, or else .nvmrc"
+        )]
+        file: Option<PinFile>,
+
+        #[clap(
+            long,
+            help = "Pin the full version, instead of only its major version. Volta and .tool-versions pins are always exact"
+        )]
+        exact: bool,
+    },
+    #[clap(
+        about = "Explain which version is used here, by tracing every place it can be set in order of priority"
+    )]
+    Why {
+        #[clap(
+            name = "VERSION",
+            help = "A version given on the command line, which takes priority over everything else"
+        )]
+        version_str: Option<String>,
+    },
+    #[clap(about = "Remove an installed version, or unregister a linked version")]
+    Uninstall {
+        #[clap(
+            name = "VERSION",
+            help = "The installed version or linked name to remove. Possible Formats: vXX, vXX.XX, vXX.XX.XX, nightly, rc/XX, vXX.XX.XX-rc.X, NAME"
+        )]
+        version_str: String,
+    },
+    #[clap(about = "List installed and linked versions, and the system NodeJS installation")]
+    List,
+    #[clap(
+        about = "Download and extract a release for any platform into a directory, without installing it"
+    )]
+    Fetch {
+        #[clap(
+            name = "VERSION",
+            help = "The version to fetch. Possible Formats: lts, latest, vXX, vXX.XX, vXX.XX.XX, nightly, rc/XX, v8-canary, vXX.XX.XX-rc.X",
             default_value = "lts"
         )]
         version_str: String,
+
+        #[clap(
+            long,
+            help = "The operating system to fetch for. Possible Values: win, darwin, linux. Defaults to the host OS"
+        )]
+        os: Option<Os>,
+
+        #[clap(long, help = "The directory to extract the release into")]
+        out: PathBuf,
+    },
+    #[clap(about = "Print the shell setup putting the current version on the PATH")]
+    Env {
+        #[clap(
+            arg_enum,
+            long,
+            help = "The shell to print the setup for. Defaults to the shell yanvm is run from"
+        )]
+        shell: Option<Shell>,
+
+        #[clap(
+            long,
+            help = "Also print a hook switching to the project's version whenever the directory changes, and back to the current version outside of projects"
+        )]
+        use_on_cd: bool,
+
+        #[clap(
+            long,
+            requires = "use-on-cd",
+            help = "Install project versions that are not installed yet when switching to them"
+        )]
+        install: bool,
+    },
+    #[clap(
+        about = "Create node, npm, npx and corepack shims, which run the version of the project they are run in"
+    )]
+    Shims,
+    #[clap(about = "Export an installed version as a reproducible tarball or OCI image layout")]
+    Export {
+        #[clap(
+            name = "VERSION",
+            help = "The installed version or linked name to export. Possible Formats: vXX, vXX.XX, vXX.XX.XX, nightly, rc/XX, vXX.XX.XX-rc.X, NAME"
+        )]
+        version_str: String,
+
+        #[clap(
+            arg_enum,
+            long,
+            default_value = "tar",
+            help = "The format to export as"
+        )]
+        format: ExportFormat,
+
+        #[clap(
+            long,
+            help = "The tarball to write, or the directory to write the OCI image layout into"
+        )]
+        out: PathBuf,
+
+        #[clap(
+            long,
+            help = "The directory to place NodeJS under. Defaults to the version's directory name for tar, and /usr/local for oci"
+        )]
+        prefix: Option<String>,
     },
 }
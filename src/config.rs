@@ -8,7 +8,8 @@ use serde::{Deserialize, Serialize};
 use crate::{
     helpers::{NoneError, ToError},
     init_dirs,
-    versions::Version,
+    installer::{source::BuildOptions, ArchiveType, DownloadOptions, InstallOptions, Installer},
+    versions::{Arch, InstalledVersion, Libc, Os},
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -21,12 +22,74 @@ pub enum ConfigError {
     TomlSerialize(#[from] toml::ser::Error),
     #[error("Failed to deserilize toml: {0}")]
     TomlDeserialize(#[from] toml::de::Error),
+    #[error("A linked version named {0} already exists")]
+    LinkExists(String),
+    #[error("{0} cannot be used as a link name, as it could be mistaken for a version")]
+    InvalidLinkName(String),
+    #[error("{0} does not look like a NodeJS installation, as it has no bin directory")]
+    InvalidLinkPath(PathBuf),
+}
+
+/// An external NodeJS installation registered under a custom name
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LinkedVersion {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// The version that the `current` directory points to
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Current {
+    Installed(InstalledVersion),
+    Linked(String),
+}
+
+impl std::fmt::Display for Current {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Current::Installed(version) => write!(f, "{}", version),
+            Current::Linked(name) => write!(f, "{}", name),
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct Config {
-    pub versions: Vec<Version>,
-    pub current: Option<Version>,
+    pub versions: Vec<InstalledVersion>,
+    pub current: Option<Current>,
+    #[serde(default)]
+    pub links: Vec<LinkedVersion>,
+    /// The architecture to install and use when none is given, defaulting to the host architecture
+    #[serde(default)]
+    pub arch: Option<Arch>,
+    /// Forces installing glibc or musl builds, instead of detecting the host's libc
+    #[serde(default)]
+    pub libc: Option<Libc>,
+    /// Archive formats in order of preference, defaulting to the format native to this OS first
+    #[serde(default)]
+    pub archive_types: Option<Vec<ArchiveType>>,
+    /// The mirror to download stable glibc releases from, instead of the official one
+    #[serde(default)]
+    pub mirror: Option<String>,
+    /// The number of ranges of an archive to download at once, when the server supports range requests
+    #[serde(default)]
+    pub download_connections: Option<usize>,
+    /// The size in bytes of each range downloaded
+    #[serde(default)]
+    pub download_chunk_size: Option<u64>,
+    /// The directory where the search for project version files stops, instead of the filesystem root
+    #[serde(default)]
+    pub version_file_boundary: Option<PathBuf>,
+    /// Whether to read `volta.node` and `engines.node` from the nearest package.json when no version file is found
+    #[serde(default)]
+    pub read_package_json: bool,
+    /// The number of parallel jobs to build from source with, defaulting to the number of CPUs
+    #[serde(default)]
+    pub build_jobs: Option<usize>,
+    /// Flags passed to the configure script when building from source, before any given on the command line
+    #[serde(default)]
+    pub configure_flags: Vec<String>,
 }
 
 impl Config {
@@ -34,6 +97,26 @@ impl Config {
         Config {
             versions: Vec::new(),
             current: None,
+            links: Vec::new(),
+            arch: None,
+            libc: None,
+            archive_types: None,
+            mirror: None,
+            download_connections: None,
+            download_chunk_size: None,
+            version_file_boundary: None,
+            read_package_json: false,
+            build_jobs: None,
+            configure_flags: Vec::new(),
+        }
+    }
+
+    /// Reads the config without creating it, for when startup time matters
+    pub fn read() -> Result<Self, ConfigError> {
+        match read_to_string(Self::config_path()?) {
+            Ok(config) => Ok(toml::from_str(&config)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::blank()),
+            Err(e) => Err(e.into()),
         }
     }
 
@@ -50,12 +133,9 @@ impl Config {
 
             Ok(config)
         } else {
-            let config = Self {
-                versions: vec![],
-                current: None,
-            };
+            let config = Self::blank();
 
-            std::fs::write(&config_path, toml::to_string(&config)?)?;
+            config.save()?;
 
             Ok(config)
         }
@@ -64,13 +144,220 @@ impl Config {
     pub fn save(&self) -> Result<(), ConfigError> {
         let config_path = Self::config_path()?;
 
-        let serialized = toml::to_string_pretty(self)?;
+        // Serializing through a value ensures plain values are emitted before tables
+        let serialized = toml::to_string_pretty(&toml::Value::try_from(self)?)?;
 
         std::fs::write(&config_path, serialized)?;
 
         Ok(())
     }
 
+    /// The architecture to use when none is explicitly requested
+    pub fn default_arch(&self) -> Arch {
+        self.arch.unwrap_or_else(Arch::detect)
+    }
+
+    /// The libc variant to use when none is explicitly requested
+    pub fn default_libc(&self) -> Libc {
+        self.libc.unwrap_or_else(Libc::detect)
+    }
+
+    /// Options for installing a release, using the configured defaults for anything not given
+    pub fn install_options(&self, arch: Option<Arch>, force: bool) -> InstallOptions {
+        let os = crate::consts::OS_STR.0;
+
+        InstallOptions {
+            os,
+            arch: arch.unwrap_or_else(|| self.default_arch()),
+            libc: self.default_libc(),
+            archive_types: self.archive_types(os),
+            mirror: self.mirror.clone(),
+            download: self.download_options(),
+            force,
+        }
+    }
+
+    /// Options for fetching a release for another platform
+    ///
+    /// Host compatibility checks are skipped, and the host's libc is only used when fetching for the host OS
+    pub fn fetch_options(&self, os: Os, arch: Option<Arch>) -> InstallOptions {
+        InstallOptions {
+            os,
+            arch: arch.unwrap_or_else(|| self.default_arch()),
+            libc: if os == crate::consts::OS_STR.0 {
+                self.default_libc()
+            } else {
+                Libc::Glibc
+            },
+            archive_types: self.archive_types(os),
+            mirror: self.mirror.clone(),
+            download: self.download_options(),
+            force: true,
+        }
+    }
+
+    /// Options for building from source, with the given jobs overriding the configured ones and the given flags added to them
+    pub fn build_options(&self, jobs: Option<usize>, configure_flags: Vec<String>) -> BuildOptions {
+        BuildOptions::new(
+            jobs.or(self.build_jobs),
+            self.configure_flags
+                .iter()
+                .cloned()
+                .chain(configure_flags)
+                .collect(),
+        )
+    }
+
+    fn download_options(&self) -> DownloadOptions {
+        DownloadOptions {
+            connections: self
+                .download_connections
+                .unwrap_or(DownloadOptions::DEFAULT.connections),
+            chunk_size: self
+                .download_chunk_size
+                .unwrap_or(DownloadOptions::DEFAULT.chunk_size),
+        }
+    }
+
+    fn archive_types(&self, os: Os) -> Vec<ArchiveType> {
+        self.archive_types
+            .clone()
+            .unwrap_or_else(|| ArchiveType::default_preference(os))
+    }
+
+    /// Finds the installed or linked version best matching the given spec
+    ///
+    /// Linked names take priority, otherwise the newest installed version matching the spec is picked.
+    /// `latest` picks the newest stable release, and `lts` the newest of an even major version, as those become LTS lines.
+    /// If no architecture is given, versions built for the default architecture are preferred
+    pub fn resolve(&self, spec: &str, arch: Option<Arch>) -> Option<Current> {
+        if let Some(link) = self.links.iter().find(|link| link.name == spec) {
+            return Some(Current::Linked(link.name.clone()));
+        }
+
+        let candidates = self
+            .versions
+            .iter()
+            .filter(|installed| match spec {
+                "latest" => installed.version.pre.is_none(),
+                "lts" => {
+                    installed.version.pre.is_none()
+                        && installed.version.major >= 4
+                        && installed.version.major % 2 == 0
+                }
+                spec => installed.version.matches(spec),
+            })
+            .filter(|installed| arch.is_none_or(|arch| installed.arch == arch))
+            .collect::<Vec<_>>();
+
+        let newest = &candidates
+            .iter()
+            .max_by_key(|installed| &installed.version)?
+            .version;
+        let mut newest = candidates
+            .into_iter()
+            .filter(|installed| &installed.version == newest)
+            .collect::<Vec<_>>();
+
+        // Detecting the defaults spawns processes, so it is skipped unless builds need breaking ties
+        if newest.len() > 1 {
+            let default_arch = self.default_arch();
+            let default_libc = self.default_libc();
+
+            newest.sort_by_key(|installed| {
+                (
+                    installed.arch == default_arch,
+                    installed.libc == default_libc,
+                )
+            });
+        }
+
+        newest
+            .pop()
+            .map(|installed| Current::Installed(installed.clone()))
+    }
+
+    /// Registers an external NodeJS installation under the given name
+    pub fn add_link(&mut self, name: String, path: PathBuf) -> Result<(), ConfigError> {
+        if matches!(
+            name.as_str(),
+            "lts" | "latest" | "system" | "current" | "nightly" | "rc" | "v8-canary"
+        ) || name
+            .trim_start_matches('v')
+            .starts_with(|c: char| c.is_ascii_digit())
+        {
+            return Err(ConfigError::InvalidLinkName(name));
+        }
+
+        if self.links.iter().any(|link| link.name == name) {
+            return Err(ConfigError::LinkExists(name));
+        }
+
+        let path = path.canonicalize()?;
+
+        if !path.join("bin").is_dir() {
+            return Err(ConfigError::InvalidLinkPath(path));
+        }
+
+        self.links.push(LinkedVersion { name, path });
+
+        Ok(())
+    }
+
+    /// The path to the installation directory of the given version
+    pub fn install_path(&self, version: &Current) -> Result<PathBuf, ConfigError> {
+        match version {
+            Current::Installed(installed) => {
+                let installer =
+                    Installer::new(installed.version.clone(), installed.arch, installed.libc);
+
+                Ok(Self::data_path()?.join(installer.parse_installer_no_ext()))
+            }
+            Current::Linked(name) => Ok(self
+                .links
+                .iter()
+                .find(|link| &link.name == name)
+                .to_error()?
+                .path
+                .clone()),
+        }
+    }
+
+    /// The path to the directory containing the `node` binary of the given version
+    pub fn bin_path(&self, version: &Current) -> Result<PathBuf, ConfigError> {
+        Ok(self.install_path(version)?.join("bin"))
+    }
+
+    pub fn data_path() -> Result<PathBuf, ConfigError> {
+        let dirs = init_dirs!().to_error()?;
+
+        Ok(dirs.data_local_dir().to_path_buf())
+    }
+
+    pub fn current_path() -> Result<PathBuf, ConfigError> {
+        Ok(Self::data_path()?.join("current"))
+    }
+
+    /// The directory linking to the man pages of the current version
+    pub fn current_man_path() -> Result<PathBuf, ConfigError> {
+        Ok(Self::data_path()?.join("current-man"))
+    }
+
+    /// The directory holding the shims created by `yanvm shims`
+    pub fn shims_path() -> Result<PathBuf, ConfigError> {
+        Ok(Self::data_path()?.join("shims"))
+    }
+
+    /// The directory holding per shell session directories, under the runtime dir where there is one
+    pub fn sessions_path() -> Result<PathBuf, ConfigError> {
+        let dirs = init_dirs!().to_error()?;
+
+        match dirs.runtime_dir() {
+            Some(runtime_dir) => Ok(runtime_dir.join("sessions")),
+            None => Ok(Self::data_path()?.join("sessions")),
+        }
+    }
+
     fn prefs_path() -> Result<PathBuf, ConfigError> {
         let dirs = init_dirs!().to_error()?;
 
@@ -83,3 +370,112 @@ impl Config {
         Ok(prefs_path.join("Config.toml"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{helpers::TempDir, versions::Version};
+
+    fn installed(version: &str, arch: Arch, libc: Libc) -> InstalledVersion {
+        InstalledVersion::new(version.parse::<Version>().unwrap(), arch, libc)
+    }
+
+    fn resolved(config: &Config, spec: &str, arch: Option<Arch>) -> Option<String> {
+        config.resolve(spec, arch).map(|version| match version {
+            Current::Installed(installed) => format!(
+                "{}-{}{}",
+                installed.version,
+                installed.arch,
+                installed.libc.suffix()
+            ),
+            Current::Linked(name) => name,
+        })
+    }
+
+    #[test]
+    fn test_add_link() {
+        let dir = TempDir::new("config-link");
+        create_dir_all(dir.join("build").join("bin")).unwrap();
+        create_dir_all(dir.join("empty")).unwrap();
+
+        let mut config = Config::blank();
+
+        for name in [
+            "lts",
+            "latest",
+            "system",
+            "nightly",
+            "18",
+            "v20",
+            "v8-canary",
+        ] {
+            assert!(matches!(
+                config.add_link(name.to_string(), dir.join("build")),
+                Err(ConfigError::InvalidLinkName(_))
+            ));
+        }
+
+        config
+            .add_link("my-build".to_string(), dir.join("build"))
+            .unwrap();
+        assert_eq!(config.links.len(), 1);
+
+        assert!(matches!(
+            config.add_link("my-build".to_string(), dir.join("build")),
+            Err(ConfigError::LinkExists(_))
+        ));
+        assert!(matches!(
+            config.add_link("empty".to_string(), dir.join("empty")),
+            Err(ConfigError::InvalidLinkPath(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut config = Config::blank();
+        config.arch = Some(Arch::X64);
+        config.libc = Some(Libc::Glibc);
+
+        config.versions = vec![
+            installed("v18.17.1", Arch::X64, Libc::Glibc),
+            installed("v20.9.0", Arch::X64, Libc::Glibc),
+            installed("v21.1.0", Arch::X64, Libc::Glibc),
+            installed("v22.0.0-rc.1", Arch::X64, Libc::Glibc),
+        ];
+        config.links = vec![LinkedVersion {
+            name: "my-build".to_string(),
+            path: PathBuf::from("/opt/node"),
+        }];
+
+        assert_eq!(resolved(&config, "my-build", None).unwrap(), "my-build");
+        assert_eq!(resolved(&config, "20", None).unwrap(), "v20.9.0-x64");
+        assert_eq!(resolved(&config, "16", None), None);
+
+        // Prereleases are skipped, and only even major versions become LTS lines
+        assert_eq!(resolved(&config, "latest", None).unwrap(), "v21.1.0-x64");
+        assert_eq!(resolved(&config, "lts", None).unwrap(), "v20.9.0-x64");
+
+        // Builds of the same version prefer the default architecture, then the default libc
+        config.versions = vec![
+            installed("v20.9.0", Arch::ARM64, Libc::Glibc),
+            installed("v20.9.0", Arch::X64, Libc::Musl),
+            installed("v20.9.0", Arch::X64, Libc::Glibc),
+            installed("v18.17.1", Arch::X64, Libc::Glibc),
+        ];
+
+        assert_eq!(resolved(&config, "20", None).unwrap(), "v20.9.0-x64");
+
+        config.libc = Some(Libc::Musl);
+        assert_eq!(resolved(&config, "20", None).unwrap(), "v20.9.0-x64-musl");
+
+        config.arch = Some(Arch::ARM64);
+        assert_eq!(resolved(&config, "20", None).unwrap(), "v20.9.0-arm64");
+
+        // An explicit architecture only matches its own builds
+        assert_eq!(
+            resolved(&config, "lts", Some(Arch::X64)).unwrap(),
+            "v20.9.0-x64-musl"
+        );
+        assert_eq!(resolved(&config, "18", Some(Arch::ARM64)), None);
+    }
+}
@@ -1,19 +1,33 @@
 use parking_lot::{const_mutex, Mutex};
 
-use crate::{config::Config, installer::ArchiveType};
+use crate::{config::Config, installer::ArchiveType, versions::Os};
 
-pub const OS_STR: (&str, ArchiveType) = {
-    if cfg!(windows) {
-        ("win", ArchiveType::Zip)
+pub const OS_STR: (Os, ArchiveType) = {
+    let os = if cfg!(windows) {
+        Os::Win
     } else if cfg!(target_os = "macos") {
-        ("darwin", ArchiveType::TarGz)
+        Os::Darwin
     } else if cfg!(target_os = "linux") {
-        ("linux", ArchiveType::TarXz)
+        Os::Linux
     } else {
         panic!("Unsupported OS");
-    }
+    };
+
+    (os, os.archive_type())
 };
 
+/// The official NodeJS release mirror
+pub const DIST_URL: &str = "https://nodejs.org/dist";
+
+/// The minimum glibc version required by official Linux builds, starting from each major version
+pub const GLIBC_REQUIREMENTS: &[(i32, (u32, u32))] = &[(18, (2, 28)), (12, (2, 17))];
+
+/// The mirror of prerelease channels, each published in a directory named after the channel
+pub const DOWNLOAD_URL: &str = "https://nodejs.org/download";
+
+/// The unofficial builds mirror, which publishes musl and glibc 2.17 builds of NodeJS
+pub const MUSL_DIST_URL: &str = "https://unofficial-builds.nodejs.org/download/release";
+
 lazy_static::lazy_static! {
     pub static ref CLIENT: reqwest::Client = reqwest::Client::new();
 }
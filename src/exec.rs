@@ -0,0 +1,60 @@
+use std::{ffi::OsString, path::Path, process::Command};
+
+use crate::{
+    config::{Config, ConfigError},
+    system,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExecError {
+    #[error("{0}")]
+    Config(#[from] ConfigError),
+    #[error("Failed to run {0}: {1}")]
+    Spawn(String, std::io::Error),
+}
+
+/// Runs the command with the given PATH, returning its exit code
+///
+/// On Unix this process is replaced by the command, so signals reach it directly and its exit code is yanvm's
+pub async fn exec(command: &[OsString], path: OsString) -> Result<i32, ExecError> {
+    let program = command[0].to_string_lossy().to_string();
+
+    let mut child = Command::new(&command[0]);
+    child.args(&command[1..]).env("PATH", path);
+
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            let mut child = tokio::process::Command::from(child)
+                .spawn()
+                .map_err(|e| ExecError::Spawn(program.clone(), e))?;
+
+            loop {
+                tokio::select! {
+                    status = child.wait() => {
+                        let status = status.map_err(|e| ExecError::Spawn(program, e))?;
+
+                        return Ok(status.code().unwrap_or(1));
+                    }
+                    // The console sends Ctrl+C to the child as well, so it is left to decide whether to exit
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+        } else {
+            use std::os::unix::process::CommandExt;
+
+            Err(ExecError::Spawn(program, child.exec()))
+        }
+    }
+}
+
+/// The PATH with the bin directory prepended, and every other directory managed by yanvm removed
+pub fn isolated_path(config: &Config, bin_path: &Path) -> Result<OsString, ExecError> {
+    let managed = system::managed_paths(config)?;
+
+    let paths = std::env::var_os("PATH").unwrap_or_default();
+
+    let isolated = std::iter::once(bin_path.to_path_buf())
+        .chain(std::env::split_paths(&paths).filter(|dir| !system::is_managed(dir, &managed)));
+
+    Ok(std::env::join_paths(isolated).unwrap_or(paths))
+}
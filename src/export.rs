@@ -0,0 +1,289 @@
+use std::{
+    fs::create_dir_all,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::versions::{Arch, Os};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("Failed to interact with IO: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to serialize json: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ArgEnum)]
+pub enum ExportFormat {
+    /// A reproducible tarball
+    Tar,
+    /// An OCI image layout directory, containing a single layer
+    Oci,
+}
+
+/// Writes the installation as a reproducible tarball, with every entry placed under the prefix
+pub fn export_tar(install_path: &Path, prefix: &str, out: &Path) -> Result<(), ExportError> {
+    let layer = build_layer(install_path, prefix)?;
+
+    if let Some(parent) = out.parent() {
+        create_dir_all(parent)?;
+    }
+
+    std::fs::write(out, layer)?;
+
+    Ok(())
+}
+
+/// Writes the installation as an OCI image layout, with a single gzipped layer placing NodeJS under the prefix
+pub fn export_oci(
+    install_path: &Path,
+    prefix: &str,
+    (os, arch): (Os, Arch),
+    ref_name: &str,
+    out: &Path,
+) -> Result<(), ExportError> {
+    let blobs = out.join("blobs").join("sha256");
+    create_dir_all(&blobs)?;
+
+    let layer = build_layer(install_path, prefix)?;
+    let diff_id = sha256_digest(&layer);
+
+    // The default gzip header has no mtime, keeping the compressed layer reproducible
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&layer)?;
+    let layer = encoder.finish()?;
+
+    let (architecture, variant) = oci_platform(arch);
+
+    let mut platform = serde_json::json!({
+        "architecture": architecture,
+        "os": oci_os(os),
+    });
+
+    if let Some(variant) = variant {
+        platform["variant"] = variant.into();
+    }
+
+    let mut config = platform.clone();
+    config["rootfs"] = serde_json::json!({
+        "type": "layers",
+        "diff_ids": [diff_id],
+    });
+    config["config"] = serde_json::json!({
+        "Env": [format!(
+            "PATH=/{}/bin:/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin",
+            prefix.trim_matches('/')
+        )],
+    });
+    let config = serde_json::to_vec(&config)?;
+
+    let manifest = serde_json::to_vec(&serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.manifest.v1+json",
+        "config": descriptor("application/vnd.oci.image.config.v1+json", &config),
+        "layers": [descriptor("application/vnd.oci.image.layer.v1.tar+gzip", &layer)],
+    }))?;
+
+    let mut manifest_descriptor =
+        descriptor("application/vnd.oci.image.manifest.v1+json", &manifest);
+    manifest_descriptor["platform"] = platform;
+    manifest_descriptor["annotations"] = serde_json::json!({
+        "org.opencontainers.image.ref.name": ref_name,
+    });
+
+    let index = serde_json::to_vec(&serde_json::json!({
+        "schemaVersion": 2,
+        "manifests": [manifest_descriptor],
+    }))?;
+
+    for blob in [&layer, &config, &manifest] {
+        let digest = sha256_digest(blob);
+        let hash = digest.trim_start_matches("sha256:");
+
+        std::fs::write(blobs.join(hash), blob)?;
+    }
+
+    std::fs::write(out.join("index.json"), index)?;
+    std::fs::write(
+        out.join("oci-layout"),
+        serde_json::to_vec(&serde_json::json!({ "imageLayoutVersion": "1.0.0" }))?,
+    )?;
+
+    Ok(())
+}
+
+fn sha256_digest(bytes: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(bytes))
+}
+
+fn descriptor(media_type: &str, blob: &[u8]) -> serde_json::Value {
+    serde_json::json!({
+        "mediaType": media_type,
+        "digest": sha256_digest(blob),
+        "size": blob.len(),
+    })
+}
+
+/// Maps an architecture to the GOARCH style names, and variants, used by OCI
+fn oci_platform(arch: Arch) -> (&'static str, Option<&'static str>) {
+    match arch {
+        Arch::X86 => ("386", None),
+        Arch::X64 => ("amd64", None),
+        Arch::ARMv6 => ("arm", Some("v6")),
+        Arch::ARMv7 => ("arm", Some("v7")),
+        Arch::ARM64 => ("arm64", None),
+        Arch::PPC64LE => ("ppc64le", None),
+        Arch::S390X => ("s390x", None),
+    }
+}
+
+fn oci_os(os: Os) -> &'static str {
+    match os {
+        Os::Win => "windows",
+        Os::Darwin => "darwin",
+        Os::Linux => "linux",
+    }
+}
+
+/// Builds an uncompressed tarball of the directory with sorted entries, and fixed timestamps and owners
+fn build_layer(install_path: &Path, prefix: &str) -> std::io::Result<Vec<u8>> {
+    let prefix = PathBuf::from(prefix.trim_matches('/'));
+
+    let mut entries = Vec::new();
+    walk_dir(install_path, Path::new(""), &mut entries)?;
+    entries.sort();
+
+    let mut builder = tar::Builder::new(Vec::new());
+
+    // Parent directories of the prefix, so the layer can be extracted onto an empty filesystem
+    let mut parents = prefix.ancestors().collect::<Vec<_>>();
+    parents.reverse();
+
+    for parent in parents
+        .into_iter()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        let mut header = reproducible_header(tar::EntryType::Directory, 0o755);
+        builder.append_data(&mut header, parent, std::io::empty())?;
+    }
+
+    for relative in entries {
+        let path = install_path.join(&relative);
+        let name = prefix.join(&relative);
+        let metadata = std::fs::symlink_metadata(&path)?;
+
+        if metadata.file_type().is_symlink() {
+            let mut header = reproducible_header(tar::EntryType::Symlink, 0o777);
+            builder.append_link(&mut header, name, std::fs::read_link(&path)?)?;
+        } else if metadata.is_dir() {
+            let mut header = reproducible_header(tar::EntryType::Directory, mode(&metadata));
+            builder.append_data(&mut header, name, std::io::empty())?;
+        } else {
+            let mut header = reproducible_header(tar::EntryType::Regular, mode(&metadata));
+            header.set_size(metadata.len());
+            builder.append_data(&mut header, name, std::fs::File::open(&path)?)?;
+        }
+    }
+
+    builder.into_inner()
+}
+
+fn reproducible_header(entry_type: tar::EntryType, mode: u32) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+
+    header.set_entry_type(entry_type);
+    header.set_mode(mode);
+    header.set_mtime(0);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_size(0);
+
+    header
+}
+
+#[cfg(not(windows))]
+fn mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(windows)]
+fn mode(metadata: &std::fs::Metadata) -> u32 {
+    if metadata.is_dir() {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+/// Collects the paths of every entry in the directory, relative to the root, without following symlinks
+fn walk_dir(root: &Path, relative: &Path, entries: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(root.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+
+        let is_dir = entry.file_type()?.is_dir();
+
+        entries.push(path.clone());
+
+        if is_dir {
+            walk_dir(root, &path, entries)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::TempDir;
+
+    #[test]
+    fn test_build_layer() {
+        let dir = TempDir::new("export");
+        let install_path = dir.join("node-v1.2.3-linux-x64");
+
+        create_dir_all(install_path.join("bin")).unwrap();
+        create_dir_all(install_path.join("lib")).unwrap();
+        std::fs::write(install_path.join("lib").join("b.js"), "b").unwrap();
+        std::fs::write(install_path.join("bin").join("node"), "node").unwrap();
+
+        let layer = build_layer(&install_path, "/usr/local").unwrap();
+
+        // Touching files must not change the layer
+        std::fs::write(install_path.join("bin").join("node"), "node").unwrap();
+        assert_eq!(build_layer(&install_path, "/usr/local").unwrap(), layer);
+
+        let mut archive = tar::Archive::new(std::io::Cursor::new(layer));
+        let entries = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let header = entry.header();
+
+                assert_eq!(header.mtime().unwrap(), 0);
+                assert_eq!(header.uid().unwrap(), 0);
+
+                entry.path().unwrap().to_string_lossy().to_string()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            entries,
+            vec![
+                "usr",
+                "usr/local",
+                "usr/local/bin",
+                "usr/local/bin/node",
+                "usr/local/lib",
+                "usr/local/lib/b.js",
+            ]
+        );
+    }
+}
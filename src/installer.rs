@@ -3,25 +3,41 @@ use std::{
     fmt::Display,
     fs::create_dir_all,
     io::{Cursor, Read},
-    path::PathBuf,
+    path::{Component, Path, PathBuf},
 };
 
-use futures_util::StreamExt;
+use futures_util::{StreamExt, TryStreamExt};
+use indicatif::{MultiProgress, ProgressBar};
+use reqwest::{
+    header::{ACCEPT_RANGES, CONTENT_LENGTH, RANGE},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{
+    config::{Config, Current},
     consts::CLIENT,
     helpers::{NoneError, ToError},
     init_dirs, init_pb,
-    links::symlink_dir,
+    links::{remove_symlink_dir, replace_symlink_dir},
     versions::{
-        index::{list_index, parse_version, LtsUnion},
-        Arch, Version,
+        index::{
+            list_index, list_shasums, parse_version, File, LtsUnion, NodeIndex, NodeIndexElement,
+        },
+        Arch, Channel, InstalledVersion, Libc, Os, Version, VersionError,
     },
 };
 
+pub mod source;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum ArchiveType {
+    #[serde(rename = "tar.gz")]
     TarGz,
+    #[serde(rename = "tar.xz")]
     TarXz,
+    #[serde(rename = "zip")]
     Zip,
 }
 
@@ -39,22 +55,84 @@ impl ArchiveType {
             ArchiveType::Zip => "zip",
         }
     }
+
+    /// The default order of preference, starting with the format native to the given OS
+    pub fn default_preference(os: Os) -> Vec<Self> {
+        let native = os.archive_type();
+
+        std::iter::once(native)
+            .chain(
+                [ArchiveType::TarXz, ArchiveType::TarGz, ArchiveType::Zip]
+                    .into_iter()
+                    .filter(|archive_type| *archive_type != native),
+            )
+            .collect()
+    }
+
+    /// Guess the archive type from a file name or url
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        [ArchiveType::TarGz, ArchiveType::TarXz, ArchiveType::Zip]
+            .into_iter()
+            .find(|archive_type| name.ends_with(&format!(".{}", archive_type.get_ext())))
+    }
 }
 
 pub struct Archive {
     pub dirs: Vec<PathBuf>,
     pub files: Vec<(PathBuf, Vec<u8>)>,
+    /// Pairs of link path and link target
+    pub symlinks: Vec<(PathBuf, PathBuf)>,
+    /// Unix permissions of files, which are applied on Unix only
+    pub modes: Vec<(PathBuf, u32)>,
 }
 
 impl Archive {
+    const fn new() -> Self {
+        Self {
+            dirs: Vec::new(),
+            files: Vec::new(),
+            symlinks: Vec::new(),
+            modes: Vec::new(),
+        }
+    }
+
     pub fn total_len(&self) -> usize {
-        self.files.len() + self.dirs.len()
+        self.files.len() + self.dirs.len() + self.symlinks.len()
+    }
+
+    /// Finds the single top level directory that every entry was extracted under
+    pub fn root_name(&self, base: &Path) -> Option<String> {
+        let mut roots = self
+            .dirs
+            .iter()
+            .chain(self.files.iter().map(|file| &file.0))
+            .chain(self.symlinks.iter().map(|symlink| &symlink.0))
+            .map(|path| {
+                path.strip_prefix(base)
+                    .ok()
+                    .and_then(|relative| relative.components().next())
+                    .map(|root| root.as_os_str().to_string_lossy().to_string())
+            });
+
+        let first = roots.next()??;
+
+        if roots.all(|root| root.as_ref() == Some(&first)) {
+            Some(first)
+        } else {
+            None
+        }
     }
 
     pub fn decompress(self) -> std::io::Result<()> {
+        self.decompress_with(init_pb!(0, "Unzipping"))
+    }
+
+    /// Writes the archive to disk, reporting progress on the given bar
+    pub fn decompress_with(self, pb: ProgressBar) -> std::io::Result<()> {
         let total = self.total_len();
 
-        let pb = init_pb!(total as u64, "Unzipping");
+        pb.set_length(total as u64);
+        pb.set_position(0);
 
         let mut extracted = 0;
 
@@ -78,127 +156,599 @@ impl Archive {
             pb.set_position(new as u64);
         }
 
+        for (path, target) in self.symlinks {
+            pb.set_message(format!("Linking {}", path.display()));
+
+            let new = min(extracted + 1, total);
+            extracted = new;
+
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent)?;
+            }
+
+            // Links left by an earlier extraction of the same archive are replaced
+            match std::fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(&path)?,
+                Ok(_) => std::fs::remove_file(&path)?,
+                Err(_) => {}
+            }
+
+            cfg_if::cfg_if! {
+                if #[cfg(windows)] {
+                    std::os::windows::fs::symlink_file(target, path)?;
+                } else {
+                    std::os::unix::fs::symlink(target, path)?;
+                }
+            }
+            pb.set_position(new as u64);
+        }
+
+        #[cfg(not(windows))]
+        for (path, mode) in self.modes {
+            use std::os::unix::fs::PermissionsExt;
+
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        }
+
         Ok(())
     }
 }
 
 pub struct Decompressor {
     bytes: Cursor<Vec<u8>>,
+    archive_type: ArchiveType,
+    pb: Option<ProgressBar>,
 }
 
 impl Decompressor {
-    pub const fn new(bytes: Cursor<Vec<u8>>) -> Self {
-        Self { bytes }
+    pub const fn new(bytes: Cursor<Vec<u8>>, archive_type: ArchiveType) -> Self {
+        Self {
+            bytes,
+            archive_type,
+            pb: None,
+        }
     }
 
-    pub fn decompress_into_mem(self, path: PathBuf) -> std::io::Result<Archive> {
-        let mut final_archive = Archive {
-            dirs: Vec::new(),
-            files: Vec::new(),
-        };
+    /// Reports progress on the given bar, instead of creating a new one
+    pub fn with_progress(mut self, pb: ProgressBar) -> Self {
+        self.pb = Some(pb);
+
+        self
+    }
+
+    pub fn decompress_into_mem(self, path: PathBuf) -> Result<Archive, InstallError> {
+        let pb = self.pb.unwrap_or_else(|| init_pb!(0, "Decompressing"));
+
+        pb.set_length(0);
+        pb.set_position(0);
+
+        match self.archive_type {
+            ArchiveType::Zip => Self::unzip_into_mem(self.bytes, path, pb),
+            ArchiveType::TarGz => {
+                Self::untar_into_mem(flate2::read::GzDecoder::new(self.bytes), path, pb)
+            }
+            ArchiveType::TarXz => {
+                Self::untar_into_mem(xz2::read::XzDecoder::new(self.bytes), path, pb)
+            }
+        }
+    }
 
-        // TODO: fix issues with cross platform decompression
-        // TODO: Remove duplicated code across platforms
-        cfg_if::cfg_if! {
-            if #[cfg(windows)] {
-                let mut unzipped = zip::read::ZipArchive::new(self.bytes)?;
+    fn unzip_into_mem(
+        bytes: Cursor<Vec<u8>>,
+        path: PathBuf,
+        pb: ProgressBar,
+    ) -> Result<Archive, InstallError> {
+        let mut final_archive = Archive::new();
 
-                let total = unzipped.len();
+        let mut unzipped = zip::read::ZipArchive::new(bytes)?;
 
-                let pb = init_pb!(total as u64, "Decompressing");
+        let total = unzipped.len();
 
-                for i in 0..total {
-                    let mut file = unzipped.by_index(i)?;
-                    let file_name = file.enclosed_name().unwrap();
+        pb.set_length(total as u64);
 
-                    pb.set_message(format!("Decompressing {}", file_name.display()));
+        for i in 0..total {
+            let mut file = unzipped.by_index(i)?;
+            let file_name = file
+                .enclosed_name()
+                .ok_or(InstallError::InvalidArchive)?
+                .to_path_buf();
 
-                    let path = path.join(file_name);
-                    if file.is_dir() {
-                        final_archive.dirs.push(path);
-                    } else if file.is_file() {
-                        let mut unpacked: Vec<u8> = Vec::new();
+            pb.set_message(format!("Decompressing {}", file_name.display()));
 
-                        file.read_to_end(&mut unpacked)?;
+            let path = path.join(file_name);
+            if file.is_dir() {
+                final_archive.dirs.push(path);
+            } else if file.is_file() {
+                let mut unpacked: Vec<u8> = Vec::new();
 
-                        final_archive.files.push((path, unpacked));
-                    }
+                file.read_to_end(&mut unpacked)?;
 
-                    pb.set_position(min(total, i) as u64);
-                }
-            } else {
-                cfg_if::cfg_if! {
-                    if #[cfg(target_os = "macos")] {
-                        let unzipped = flate2::read::GzDecoder::new(self.bytes);
-                    } else {
-                        let unzipped = xz2::read::XzDecoder::new(self.bytes);
-                    }
+                if let Some(mode) = file.unix_mode() {
+                    final_archive.modes.push((path.clone(), mode));
                 }
 
-                let mut archive = tar::Archive::new(unzipped);
+                final_archive.files.push((path, unpacked));
+            }
+
+            pb.set_position(min(total, i) as u64);
+        }
 
-                let mut entries = archive.entries()?.collect::<Result<Vec<_>, _>>()?;
+        Ok(final_archive)
+    }
 
-                let total = entries.len();
+    fn untar_into_mem(
+        decoder: impl Read,
+        path: PathBuf,
+        pb: ProgressBar,
+    ) -> Result<Archive, InstallError> {
+        let mut final_archive = Archive::new();
 
-                let pb = init_pb!(total as u64, "Decompressing");
+        let mut archive = tar::Archive::new(decoder);
 
-                for i in 0..total {
-                    let entry = entries.get_mut(i).unwrap();
+        // Entries have to be read in order, as the archive is a stream, so the total is unknown up front
 
-                    let path = path.join(entry.path()?);
-                    let header = entry.header().entry_type().to_owned();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
 
-                    match header {
-                        tar::EntryType::Directory => final_archive.dirs.push(path),
-                        _ => {
-                            let mut unpacked: Vec<u8> = Vec::new();
+            let path = path.join(enclosed(&entry.path()?)?);
+            let header = entry.header().entry_type().to_owned();
 
-                            entry.read_to_end(&mut unpacked)?;
+            pb.set_message(format!("Decompressing {}", path.display()));
 
-                            final_archive.files.push((path, unpacked));
-                        },
+            match header {
+                tar::EntryType::Directory => final_archive.dirs.push(path),
+                tar::EntryType::Symlink => {
+                    if let Some(target) = entry.link_name()? {
+                        final_archive.symlinks.push((path, target.to_path_buf()));
                     }
+                }
+                header if header.is_file() => {
+                    let mut unpacked: Vec<u8> = Vec::new();
+
+                    entry.read_to_end(&mut unpacked)?;
 
-                    pb.set_position(min(i, total) as u64);
+                    final_archive
+                        .modes
+                        .push((path.clone(), entry.header().mode()?));
+                    final_archive.files.push((path, unpacked));
                 }
+                _ => {}
             }
+
+            pb.inc_length(1);
+            pb.inc(1);
         }
+
         Ok(final_archive)
     }
 }
 
+/// Checks that an archive entry stays inside the directory it is extracted to
+fn enclosed(entry: &Path) -> Result<&Path, InstallError> {
+    let enclosed = entry
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+    if enclosed {
+        Ok(entry)
+    } else {
+        Err(InstallError::InvalidArchive)
+    }
+}
+
 pub struct NodeBinary {
     bytes: Cursor<Vec<u8>>,
-    binary_name: String,
+    version: InstalledVersion,
+    archive_type: ArchiveType,
 }
 
 impl NodeBinary {
-    pub fn new(bytes: Vec<u8>, name: String) -> Self {
+    pub fn new(bytes: Vec<u8>, version: InstalledVersion, archive_type: ArchiveType) -> Self {
         let cursor = Cursor::new(bytes);
 
         Self {
             bytes: cursor,
-            binary_name: name,
+            version,
+            archive_type,
         }
     }
 
-    pub async fn unzip_file(self) -> Result<(), InstallError> {
-        let dirs = init_dirs!().to_error()?;
-        let path = dirs.data_local_dir().to_path_buf();
-
-        let archive = Decompressor::new(self.bytes).decompress_into_mem(path.clone())?;
+    /// Extracts the binary into the given directory, without registering it
+    pub fn extract(self, path: PathBuf) -> Result<(), InstallError> {
+        let archive = Decompressor::new(self.bytes, self.archive_type).decompress_into_mem(path)?;
 
         archive.decompress()?;
 
-        let bin_path = path.join(self.binary_name).join("bin");
-        let bin_target_path = path.join("current");
+        Ok(())
+    }
+
+    /// Extracts the binary like [`NodeBinary::extract`], reporting progress on the given bar
+    pub fn extract_with(self, path: PathBuf, pb: ProgressBar) -> Result<(), InstallError> {
+        pb.set_message(format!("Extracting {}", self.version.version));
+
+        let archive = Decompressor::new(self.bytes, self.archive_type)
+            .with_progress(pb.clone())
+            .decompress_into_mem(path)?;
+
+        archive.decompress_with(pb)?;
+
+        Ok(())
+    }
+}
+
+/// A release installed by [`install_many`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Installed {
+    New(InstalledVersion),
+    /// The release was installed before, so it was not downloaded again
+    Existing(InstalledVersion),
+}
+
+impl Installed {
+    pub const fn version(&self) -> &InstalledVersion {
+        match self {
+            Installed::New(version) | Installed::Existing(version) => version,
+        }
+    }
+
+    pub fn into_version(self) -> InstalledVersion {
+        match self {
+            Installed::New(version) | Installed::Existing(version) => version,
+        }
+    }
+}
+
+/// Installs several releases at once, resolving every spec against a single fetched index per mirror
+///
+/// Up to `parallel` releases are downloaded and extracted concurrently, each with its own progress bar.
+/// Specs resolving to an already resolved or installed release are skipped, and when switching, the first version installed becomes the current version
+pub async fn install_many(
+    specs: &[String],
+    options: &InstallOptions,
+    parallel: usize,
+    switch: bool,
+) -> Result<Vec<(String, Result<Installed, InstallError>)>, InstallError> {
+    let installed = crate::consts::CONFIG.lock().versions.clone();
+
+    let results =
+        download_many(specs, options, parallel, &Config::data_path()?, &installed).await?;
+
+    let mut config = crate::consts::CONFIG.lock();
+
+    for version in results.iter().filter_map(|(_, result)| match result {
+        Ok(Installed::New(version)) => Some(version),
+        _ => None,
+    }) {
+        if !config.versions.contains(version) {
+            config.versions.push(version.clone());
+        }
+    }
+
+    match results.iter().find_map(|(_, result)| result.as_ref().ok()) {
+        Some(first) if switch => {
+            link_current(&mut config, Current::Installed(first.version().clone()))?
+        }
+        _ => {}
+    }
+
+    config.save()?;
+
+    Ok(results)
+}
+
+/// Downloads and extracts the releases matching the specs into the data directory, without registering them
+///
+/// Releases in `installed` are reported as existing instead of being downloaded again
+async fn download_many(
+    specs: &[String],
+    options: &InstallOptions,
+    parallel: usize,
+    data_path: &Path,
+    installed: &[InstalledVersion],
+) -> Result<Vec<(String, Result<Installed, InstallError>)>, InstallError> {
+    let mut indexes: Vec<(String, NodeIndex)> = Vec::new();
+
+    for spec in specs {
+        let dist_url = options.dist_url(Channel::from_spec(spec));
+
+        if !indexes.iter().any(|(url, _)| url == &dist_url) {
+            let index = list_index(&dist_url).await?;
+            indexes.push((dist_url, index));
+        }
+    }
+
+    let mut results = Vec::new();
+    let mut installers: Vec<(String, Installer)> = Vec::new();
+
+    for spec in specs {
+        let dist_url = options.dist_url(Channel::from_spec(spec));
+        let (_, index) = indexes
+            .iter()
+            .find(|(url, _)| url == &dist_url)
+            .to_error()?;
+
+        match Installer::from_index(spec, index, &dist_url, options).await {
+            Ok(installer) if installed.contains(&installer.installed()) => {
+                results.push((spec.clone(), Ok(Installed::Existing(installer.installed()))))
+            }
+            Ok(installer)
+                if installers
+                    .iter()
+                    .any(|(_, resolved)| resolved.installed() == installer.installed()) => {}
+            Ok(installer) => installers.push((spec.clone(), installer)),
+            Err(e) => results.push((spec.clone(), Err(e))),
+        }
+    }
+
+    let multi = MultiProgress::new();
+    let bars = installers
+        .iter()
+        .map(|(_, installer)| {
+            multi.add(init_pb!(
+                0,
+                format!("Waiting to install {}", installer.version)
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    // Bars added to a MultiProgress are only drawn while it is joined
+    let multi = tokio::task::spawn_blocking(move || multi.join());
+
+    let downloaded = futures_util::stream::iter(installers.into_iter().zip(bars))
+        .map(|((spec, installer), pb)| {
+            let data_path = data_path.to_path_buf();
+
+            async move {
+                let result: Result<Installed, InstallError> = async {
+                    let binary = installer.download_binary_with(pb.clone()).await?;
+                    let version = binary.version.clone();
+
+                    let extract_pb = pb.clone();
+                    tokio::task::spawn_blocking(move || binary.extract_with(data_path, extract_pb))
+                        .await??;
+
+                    Ok(Installed::New(version))
+                }
+                .await;
+
+                pb.finish_and_clear();
+
+                (spec, result)
+            }
+        })
+        .buffer_unordered(parallel.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    multi.await??;
+
+    results.extend(downloaded);
+    results.sort_by_key(|(spec, _)| specs.iter().position(|s| s == spec));
+
+    Ok(results)
+}
+
+/// Points the `current` directory at the given version, and records it in the config
+pub fn link_current(config: &mut Config, version: Current) -> Result<(), InstallError> {
+    let bin_path = config.bin_path(&version)?;
+    let bin_target_path = Config::current_path()?;
+
+    info!("Begining symlink from {}", bin_path.display());
+
+    replace_symlink_dir(bin_path, bin_target_path)?;
+
+    let man_path = config.install_path(&version)?.join("share").join("man");
+    let man_target_path = Config::current_man_path()?;
+
+    if man_path.is_dir() {
+        replace_symlink_dir(man_path, man_target_path)?;
+    } else {
+        remove_symlink_dir(man_target_path)?;
+    }
+
+    config.current = Some(version);
+    config.save()?;
+
+    Ok(())
+}
+
+/// Removes the `current` directory, so that a NodeJS installation elsewhere on the PATH takes over
+pub fn unlink_current(config: &mut Config) -> Result<(), InstallError> {
+    remove_symlink_dir(Config::current_path()?)?;
+    remove_symlink_dir(Config::current_man_path()?)?;
+
+    config.current = None;
+    config.save()?;
+
+    Ok(())
+}
+
+/// Removes the given version, unlinking it first if it is the current version
+///
+/// Linked versions are only unregistered, as their directories are not managed by yanvm
+pub fn uninstall(config: &mut Config, version: Current) -> Result<(), InstallError> {
+    if config.current.as_ref() == Some(&version) {
+        unlink_current(config)?;
+    }
+
+    match &version {
+        Current::Installed(installed) => {
+            let install_path = config.install_path(&version)?;
+
+            if install_path.exists() {
+                std::fs::remove_dir_all(install_path)?;
+            }
+
+            config.versions.retain(|v| v != installed);
+        }
+        Current::Linked(name) => config.links.retain(|link| &link.name != name),
+    }
+
+    config.save()?;
+
+    Ok(())
+}
+
+/// Finds the newest release in the index matching the spec
+fn find_release<'a>(index: &'a NodeIndex, spec: &str) -> Option<&'a NodeIndexElement> {
+    match spec {
+        // Prereleases are published separately, but are never picked by `latest` or `lts` regardless
+        "latest" => index.iter().find(|i| !i.version.contains('-')),
+        "lts" => index
+            .iter()
+            .find(|i| i.lts != LtsUnion::Bool(false) && !i.version.contains('-')),
+        spec => index
+            .iter()
+            .find(|i| i.version.parse::<Version>().is_ok_and(|v| v.matches(spec))),
+    }
+}
+
+/// Finds the release closest to the given one in the index that satisfies the predicate
+fn nearest_release<'a>(
+    index: &'a NodeIndex,
+    element: &NodeIndexElement,
+    predicate: impl Fn(&NodeIndexElement) -> bool,
+) -> Option<&'a NodeIndexElement> {
+    let position = index.iter().position(|i| i == element)?;
+
+    (1..index.len()).find_map(|distance| {
+        // Prefer older releases, as newer ones are more likely to have dropped support
+        [
+            position.checked_add(distance),
+            position.checked_sub(distance),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| index.get(i))
+        .find(|i| predicate(i))
+    })
+}
+
+/// Downloads the file at the given url into memory, reporting progress on the given bar
+async fn download(url: &str, pb: ProgressBar) -> Result<Vec<u8>, InstallError> {
+    let res = CLIENT.get(url).send().await?.error_for_status()?;
+
+    let total_size = res.content_length().unwrap_or(0);
+
+    pb.set_length(total_size);
+    pb.set_position(0);
+
+    // download chunks
+    let mut downloaded: u64 = 0;
+    let mut stream = res.bytes_stream();
+
+    let mut bytes: Vec<u8> = Vec::new();
+
+    while let Some(item) = stream.next().await {
+        let chunk = item?;
+        let iter = chunk.iter();
+        bytes.append(&mut iter.copied().collect::<Vec<u8>>());
+        let new = min(downloaded + (chunk.len() as u64), total_size);
+        downloaded = new;
+        pb.set_position(new);
+    }
+
+    Ok(bytes)
+}
+
+/// Downloads the file in concurrent ranges, which are reassembled in order
+///
+/// Falls back to a single request when the server does not advertise range support, or the file fits in one chunk
+async fn download_chunked(
+    url: &str,
+    pb: ProgressBar,
+    options: DownloadOptions,
+) -> Result<Vec<u8>, InstallError> {
+    if options.connections <= 1 {
+        return download(url, pb).await;
+    }
+
+    // Servers that refuse HEAD requests are downloaded from in a single stream
+    let head = match CLIENT.head(url).send().await {
+        Ok(head) if head.status().is_success() => head,
+        _ => return download(url, pb).await,
+    };
+
+    let accepts_ranges = head
+        .headers()
+        .get(ACCEPT_RANGES)
+        .is_some_and(|value| value == "bytes");
+
+    // The body of a HEAD response is empty, so the length has to be read from the header
+    let total_size = head
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+
+    let total_size = match total_size {
+        Some(total_size) if accepts_ranges && total_size > options.chunk_size => total_size,
+        _ => return download(url, pb).await,
+    };
+
+    pb.set_length(total_size);
+    pb.set_position(0);
+
+    let chunks = futures_util::stream::iter(chunk_ranges(total_size, options.chunk_size))
+        .map(|(start, end)| {
+            let pb = pb.clone();
+
+            async move {
+                let res = CLIENT
+                    .get(url)
+                    .header(RANGE, format!("bytes={}-{}", start, end))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+
+                // Servers may ignore the range and respond with the whole file
+                if res.status() != StatusCode::PARTIAL_CONTENT {
+                    return Err(InstallError::RangeNotHonored(url.to_string()));
+                }
+
+                let mut chunk = Vec::with_capacity((end - start + 1) as usize);
+                let mut stream = res.bytes_stream();
+
+                while let Some(item) = stream.next().await {
+                    let item = item?;
+
+                    chunk.extend_from_slice(&item);
+                    pb.inc(item.len() as u64);
+                }
+
+                if chunk.len() as u64 != end - start + 1 {
+                    return Err(InstallError::RangeNotHonored(url.to_string()));
+                }
+
+                Ok(chunk)
+            }
+        })
+        .buffered(options.connections)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    Ok(chunks.concat())
+}
+
+/// Splits a file into inclusive byte ranges of at most the chunk size
+fn chunk_ranges(total_size: u64, chunk_size: u64) -> Vec<(u64, u64)> {
+    let chunk_size = chunk_size.max(1);
 
-        info!("Begining symlink from {}", bin_path.display());
+    (0..total_size)
+        .step_by(chunk_size as usize)
+        .map(|start| (start, min(start + chunk_size, total_size) - 1))
+        .collect()
+}
 
-        symlink_dir(bin_path, bin_target_path)?;
+fn verify_sha256(bytes: &[u8], expected: &str) -> Result<(), InstallError> {
+    let actual = format!("{:x}", Sha256::digest(bytes));
 
+    if actual.eq_ignore_ascii_case(expected.trim()) {
         Ok(())
+    } else {
+        Err(InstallError::ChecksumMismatch {
+            expected: expected.trim().to_string(),
+            actual,
+        })
     }
 }
 
@@ -210,107 +760,452 @@ pub enum InstallError {
     Reqwest(#[from] reqwest::Error),
     #[error("Failed to interact with IO: {0}")]
     Io(#[from] std::io::Error),
-    #[cfg(windows)]
     #[error("Failed to decompress file: {0}")]
     DecompressError(#[from] zip::result::ZipError),
     #[error("Interaction with config")]
     ConfigError(#[from] crate::config::ConfigError),
+    #[error("{0}")]
+    Version(#[from] VersionError),
+    #[error("Checksum mismatch. Expected {expected}, found {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("Unsupported archive {0}. Expected a .tar.xz, .tar.gz or .zip archive")]
+    UnsupportedArchive(String),
+    #[error(
+        "{name} is not published in any of the preferred formats ({}). Published formats: {}",
+        preferred.join(", "),
+        published.join(", ")
+    )]
+    NoPreferredArchive {
+        name: String,
+        preferred: Vec<String>,
+        published: Vec<String>,
+    },
+    #[error("Archive does not contain a single NodeJS installation directory")]
+    InvalidArchive,
+    #[error(
+        "NodeJS {version} is not published for {platform}\nAvailable platforms: {}\n{nearest}",
+        available.join(", ")
+    )]
+    UnsupportedPlatform {
+        version: Version,
+        platform: String,
+        available: Vec<String>,
+        nearest: String,
+    },
+    #[error("No NodeJS release matches {0}")]
+    VersionNotFound(String),
+    #[error("NodeJS {0} does not publish a source tarball in any of the preferred formats")]
+    SourceNotPublished(String),
+    #[error("Building NodeJS from source is not supported on Windows")]
+    SourceUnsupported,
+    #[error("Building NodeJS from source only targets this system ({host}), not {platform}")]
+    SourceNotHost { platform: String, host: String },
+    #[error("{step} NodeJS failed, see {} for the build output", log.display())]
+    BuildFailed { step: String, log: PathBuf },
+    #[error("The server did not honor a range request for {0}. Set `download_connections = 1` in the config to disable range requests")]
+    RangeNotHonored(String),
+    #[error("Failed to join task: {0}")]
+    Join(#[from] tokio::task::JoinError),
+    #[error(
+        "NodeJS {version} requires glibc {}.{}, but this system has glibc {}.{}\n{suggestion}\nPass --force to install anyway",
+        required.0,
+        required.1,
+        found.0,
+        found.1
+    )]
+    GlibcTooOld {
+        version: Version,
+        required: (u32, u32),
+        found: (u32, u32),
+        suggestion: String,
+    },
+}
+
+/// Options controlling which build of a release gets installed
+#[derive(Clone)]
+pub struct InstallOptions {
+    pub os: Os,
+    pub arch: Arch,
+    pub libc: Libc,
+    /// Archive formats in order of preference, the first one published by the release is downloaded
+    pub archive_types: Vec<ArchiveType>,
+    /// Overrides the official mirror of stable glibc releases
+    pub mirror: Option<String>,
+    pub download: DownloadOptions,
+    /// Skips compatibility checks against the host
+    pub force: bool,
+}
+
+/// Options controlling how archives are downloaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadOptions {
+    /// The number of ranges downloaded at once, a single connection disables range requests
+    pub connections: usize,
+    /// The size in bytes of each range
+    pub chunk_size: u64,
+}
+
+impl DownloadOptions {
+    pub const DEFAULT: Self = Self {
+        connections: 4,
+        chunk_size: 8 * 1024 * 1024,
+    };
+}
+
+impl InstallOptions {
+    /// The mirror to resolve releases of the given channel from
+    pub fn dist_url(&self, channel: Channel) -> String {
+        match (&self.mirror, channel, self.libc) {
+            (Some(mirror), Channel::Release, Libc::Glibc) => {
+                mirror.trim_end_matches('/').to_string()
+            }
+            _ => channel.dist_url(self.libc),
+        }
+    }
 }
 
 pub struct Installer {
     version: Version,
+    os: Os,
     arch: Arch,
+    libc: Libc,
+    archive_type: ArchiveType,
+    sha256: Option<String>,
+    /// The mirror the release was resolved from, defaulting to the official mirror of its channel
+    dist_url: Option<String>,
+    download: DownloadOptions,
 }
 
 impl Installer {
-    pub const fn new(version: Version, arch: Arch) -> Self {
-        Self { version, arch }
+    pub const fn new(version: Version, arch: Arch, libc: Libc) -> Self {
+        Self {
+            version,
+            os: crate::consts::OS_STR.0,
+            arch,
+            libc,
+            archive_type: crate::consts::OS_STR.1,
+            sha256: None,
+            dist_url: None,
+            download: DownloadOptions::DEFAULT,
+        }
+    }
+
+    fn dist_url(&self) -> String {
+        self.dist_url
+            .clone()
+            .unwrap_or_else(|| self.version.channel().dist_url(self.libc))
     }
 
     pub fn get_installer_link(&self) -> String {
         format!(
-            "https://nodejs.org/dist/{}/{installer}",
+            "{}/{}/{installer}",
+            self.dist_url(),
             self.version,
             installer = self.parse_installer()
         )
     }
 
-    pub fn parse_installer_no_ext(&self) -> String {
-        let os = crate::consts::OS_STR.0;
+    /// Reverses [`Installer::parse_installer_no_ext`], parsing the name of an extracted NodeJS directory
+    pub fn from_installer_name(name: &str) -> Result<Self, InstallError> {
+        let (os, _) = crate::consts::OS_STR;
+
+        let parts = name
+            .strip_prefix("node-")
+            .and_then(|rest| rest.split_once(&format!("-{}-", os)))
+            .ok_or(InstallError::InvalidArchive)?;
+
+        let (arch, libc) = [Libc::Musl, Libc::Glibc217]
+            .into_iter()
+            .find_map(|libc| Some((parts.1.strip_suffix(libc.suffix())?, libc)))
+            .unwrap_or((parts.1, Libc::Glibc));
+
+        let installer = Installer::new(parts.0.parse()?, arch.parse()?, libc);
 
-        format!("node-{}-{}-{}", self.version, os, self.arch)
+        if installer.parse_installer_no_ext() != name {
+            return Err(InstallError::InvalidArchive);
+        }
+
+        Ok(installer)
+    }
+
+    pub fn installed(&self) -> InstalledVersion {
+        InstalledVersion::new(self.version.clone(), self.arch, self.libc)
+    }
+
+    pub fn parse_installer_no_ext(&self) -> String {
+        format!(
+            "node-{}-{}-{}{}",
+            self.version,
+            self.os,
+            self.arch,
+            self.libc.suffix()
+        )
     }
 
     pub fn parse_installer(&self) -> String {
-        let ext = crate::consts::OS_STR.1;
+        format!("{}.{}", self.parse_installer_no_ext(), self.archive_type)
+    }
+
+    /// Finds the newest release matching the spec, which may be `lts`, `latest` or a possibly partial version
+    ///
+    /// Specs naming a prerelease channel, such as `nightly`, `rc/20` or `v21.0.0-rc.1`, are resolved from that channel's index
+    pub async fn from_spec(spec: &str, options: &InstallOptions) -> Result<Self, InstallError> {
+        let dist_url = options.dist_url(Channel::from_spec(spec));
+        let index = list_index(&dist_url).await?;
 
-        format!("{}.{}", self.parse_installer_no_ext(), ext)
+        Self::from_index(spec, &index, &dist_url, options).await
     }
 
-    pub async fn lts_version() -> Result<Self, InstallError> {
-        let index = list_index().await?;
-        let version_string = index
-            .iter()
-            .find(|i| i.lts != LtsUnion::Bool(false))
-            .to_error()?
-            .version
-            .to_string();
+    /// Finds the newest release matching the spec in an already fetched index of the given mirror
+    pub async fn from_index(
+        spec: &str,
+        index: &NodeIndex,
+        dist_url: &str,
+        options: &InstallOptions,
+    ) -> Result<Self, InstallError> {
+        let element = find_release(index, spec)
+            .ok_or_else(|| InstallError::VersionNotFound(spec.to_string()))?;
+
+        let version: Version = element.version.parse()?;
+        let mut installer = Installer::new(version, options.arch, options.libc);
+        installer.os = options.os;
+        installer.dist_url = Some(dist_url.to_string());
+        installer.download = options.download;
+
+        installer.check_published(element, index)?;
+
+        if !options.force {
+            installer.check_glibc(index)?;
+        }
 
-        let version: Version = parse_version(version_string).into();
-        let installer = Installer::new(version, Arch::new());
+        let shasums = list_shasums(dist_url, &element.version).await?;
+        installer.pick_archive(&shasums, &options.archive_types)?;
 
         Ok(installer)
     }
 
-    pub async fn latest_version() -> Result<Self, InstallError> {
-        let index = list_index().await?;
+    /// Picks the most preferred archive format that the release publishes, remembering its checksum
+    fn pick_archive(
+        &mut self,
+        shasums: &[(String, String)],
+        preferred: &[ArchiveType],
+    ) -> Result<(), InstallError> {
+        let name = self.parse_installer_no_ext();
 
-        let version: Version = parse_version(&index[0].version).into();
+        let published = shasums
+            .iter()
+            .filter_map(|(hash, file_name)| {
+                let archive_type = ArchiveType::from_file_name(file_name)?;
 
-        let installer = Installer::new(version, Arch::new());
+                (file_name == &format!("{}.{}", name, archive_type)).then_some((archive_type, hash))
+            })
+            .collect::<Vec<_>>();
 
-        Ok(installer)
+        let (archive_type, hash) = preferred
+            .iter()
+            .find_map(|preferred| {
+                published
+                    .iter()
+                    .find(|(archive_type, _)| archive_type == preferred)
+            })
+            .ok_or_else(|| InstallError::NoPreferredArchive {
+                name: name.clone(),
+                preferred: preferred.iter().map(ToString::to_string).collect(),
+                published: published
+                    .iter()
+                    .map(|(archive_type, _)| archive_type.to_string())
+                    .collect(),
+            })?;
+
+        self.archive_type = *archive_type;
+        self.sha256 = Some(hash.to_string());
+
+        Ok(())
+    }
+
+    /// Refuses to install a platform that the release does not publish a binary for
+    ///
+    /// The error lists the platforms the release does publish, and the nearest release that publishes the requested platform
+    fn check_published(
+        &self,
+        element: &NodeIndexElement,
+        index: &NodeIndex,
+    ) -> Result<(), InstallError> {
+        let file = File::for_platform(self.os, self.arch, self.libc);
+
+        let published = |element: &NodeIndexElement| {
+            file.as_ref()
+                .is_some_and(|file| element.files.contains(file))
+        };
+
+        if published(element) {
+            return Ok(());
+        }
+
+        let platform = format!("{}-{}{}", self.os, self.arch, self.libc.suffix());
+
+        let available = element
+            .files
+            .iter()
+            .filter(|file| !matches!(file, File::Unknown | File::Src | File::Headers))
+            .map(|file| file.to_string())
+            .collect();
+
+        let nearest = match nearest_release(index, element, published) {
+            Some(nearest) => format!(
+                "The nearest release published for {} is {}",
+                platform, nearest.version
+            ),
+            None => format!("No release is published for {}", platform),
+        };
+
+        let nearest = if element.files.contains(&File::Src) {
+            format!(
+                "{}\nPass --from-source to build {} instead",
+                nearest, element.version
+            )
+        } else {
+            nearest
+        };
+
+        Err(InstallError::UnsupportedPlatform {
+            version: self.version.clone(),
+            platform,
+            available,
+            nearest,
+        })
+    }
+
+    /// Refuses to install an official Linux build that requires a newer glibc than the host has
+    ///
+    /// Such installs would otherwise succeed, with `node` failing at startup due to missing symbols
+    fn check_glibc(&self, index: &NodeIndex) -> Result<(), InstallError> {
+        if crate::consts::OS_STR.0 != Os::Linux || self.os != Os::Linux || self.libc != Libc::Glibc
+        {
+            return Ok(());
+        }
+
+        let (required, found) = match (self.version.required_glibc(), Libc::glibc_version()) {
+            (Some(required), Some(found)) if found < required => (required, found),
+            _ => return Ok(()),
+        };
+
+        let compatible = index
+            .iter()
+            .map(|element| Version::from(parse_version(&element.version)))
+            .find(|version| version.required_glibc().is_none_or(|glibc| glibc <= found));
+
+        let mut suggestion = match compatible {
+            Some(version) => format!(
+                "{} is the newest release compatible with this system.",
+                version
+            ),
+            None => "No official release is compatible with this system.".to_string(),
+        };
+
+        if found >= (2, 17) && self.arch == Arch::X64 {
+            suggestion.push_str(
+                " Alternatively, set `libc = \"glibc-217\"` in the config to use the unofficial glibc 2.17 builds.",
+            );
+        }
+
+        Err(InstallError::GlibcTooOld {
+            version: self.version.clone(),
+            required,
+            found,
+            suggestion,
+        })
     }
 
     pub async fn download_binary(&self) -> Result<NodeBinary, InstallError> {
+        self.download_binary_with(init_pb!(0)).await
+    }
+
+    /// Downloads the binary like [`Installer::download_binary`], reporting progress on the given bar
+    pub async fn download_binary_with(&self, pb: ProgressBar) -> Result<NodeBinary, InstallError> {
         let link = self.get_installer_link();
 
-        let res = CLIENT.get(link.clone()).send().await?;
+        pb.set_message(format!("Downloading {}", self.version));
+
+        let bytes = download_chunked(&link, pb, self.download).await?;
+
+        if let Some(expected) = &self.sha256 {
+            verify_sha256(&bytes, expected)?;
+        }
+
+        Ok(NodeBinary::new(bytes, self.installed(), self.archive_type))
+    }
+
+    /// Installs a NodeJS archive from the local filesystem
+    pub async fn install_archive(
+        path: impl AsRef<Path>,
+        sha256: Option<&str>,
+    ) -> Result<Self, InstallError> {
+        let path = path.as_ref();
+        let file_name = path.to_string_lossy();
+
+        let archive_type = Self::check_archive_type(&file_name)?;
+
+        let bytes = tokio::fs::read(path).await?;
+
+        Self::install_archive_bytes(bytes, archive_type, sha256)
+    }
 
-        let total_size = res.content_length().unwrap_or(0);
+    /// Downloads and installs a NodeJS archive from an arbitrary url
+    pub async fn install_url(url: &str, sha256: Option<&str>) -> Result<Self, InstallError> {
+        let archive_type = Self::check_archive_type(url)?;
 
-        let pb = init_pb!(total_size, format!("Downloading {}", self.version));
+        let bytes = download(url, init_pb!(0, format!("Downloading {}", url))).await?;
 
-        // download chunks
-        // let mut file = File::create(path.clone())?;
-        let mut downloaded: u64 = 0;
-        let mut stream = res.bytes_stream();
+        Self::install_archive_bytes(bytes, archive_type, sha256)
+    }
 
-        let mut bytes: Vec<u8> = Vec::new();
+    fn check_archive_type(file_name: &str) -> Result<ArchiveType, InstallError> {
+        ArchiveType::from_file_name(file_name)
+            .ok_or_else(|| InstallError::UnsupportedArchive(file_name.to_string()))
+    }
 
-        while let Some(item) = stream.next().await {
-            let chunk = item?;
-            let iter = chunk.iter();
-            bytes.append(&mut iter.copied().collect::<Vec<u8>>());
-            let new = min(downloaded + (chunk.len() as u64), total_size);
-            downloaded = new;
-            pb.set_position(new);
+    fn install_archive_bytes(
+        bytes: Vec<u8>,
+        archive_type: ArchiveType,
+        sha256: Option<&str>,
+    ) -> Result<Self, InstallError> {
+        if let Some(expected) = sha256 {
+            verify_sha256(&bytes, expected)?;
         }
 
+        let dirs = init_dirs!().to_error()?;
+        let path = dirs.data_local_dir().to_path_buf();
+
+        let archive = Decompressor::new(Cursor::new(bytes), archive_type)
+            .decompress_into_mem(path.clone())?;
+
+        let binary_name = archive
+            .root_name(&path)
+            .ok_or(InstallError::InvalidArchive)?;
+        let mut installer = Self::from_installer_name(&binary_name)?;
+        installer.archive_type = archive_type;
+
+        archive.decompress()?;
+
         let mut config = crate::consts::CONFIG.lock();
 
-        config.versions.push(self.version);
-        config.save()?;
+        if !config.versions.contains(&installer.installed()) {
+            config.versions.push(installer.installed());
+        }
+
+        link_current(&mut config, Current::Installed(installer.installed()))?;
 
-        Ok(NodeBinary::new(bytes, self.parse_installer_no_ext()))
+        Ok(installer)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::{serve, TempDir};
 
-    static VERSION: Version = Version::new(1, 2, 3);
+    const VERSION: Version = Version::new(1, 2, 3);
 
     #[test]
     fn test_version() {
@@ -321,10 +1216,135 @@ mod tests {
         assert_eq!(VERSION.to_string(), "v1.2.3");
     }
 
+    #[test]
+    fn test_version_matches() {
+        assert!(VERSION.matches("1"));
+        assert!(VERSION.matches("v1.2"));
+        assert!(VERSION.matches("1.2.3"));
+
+        assert!(!VERSION.matches(""));
+        assert!(!VERSION.matches("v1.3"));
+        assert!(!VERSION.matches("1.2.3.4"));
+        assert!(!VERSION.matches("my-patched"));
+    }
+
+    #[test]
+    fn test_prerelease() {
+        let rc: Version = "v21.0.0-rc.1".parse().unwrap();
+        let nightly: Version = "v21.0.0-nightly20230801d2d9cb6d2c".parse().unwrap();
+        let canary: Version = "v21.0.0-v8-canary20230801abcdef".parse().unwrap();
+
+        assert_eq!(rc.to_string(), "v21.0.0-rc.1");
+        assert_eq!(rc.channel(), Channel::Rc);
+        assert_eq!(nightly.channel(), Channel::Nightly);
+        assert_eq!(canary.channel(), Channel::V8Canary);
+        assert_eq!(VERSION.channel(), Channel::Release);
+        assert!("v21.0.0-".parse::<Version>().is_err());
+
+        assert!(rc.matches("rc"));
+        assert!(rc.matches("rc/21"));
+        assert!(rc.matches("v21.0.0-rc.1"));
+        assert!(!rc.matches("rc/20"));
+        assert!(!rc.matches("nightly"));
+        assert!(!rc.matches("21"));
+        assert!(canary.matches("v8-canary/21.0"));
+        assert!(!VERSION.matches("nightly"));
+
+        assert!(rc < Version::new(21, 0, 0));
+        assert!(rc < "v21.0.0-rc.10".parse().unwrap());
+        assert!(rc > Version::new(20, 9, 0));
+
+        assert_eq!(Channel::from_spec("nightly"), Channel::Nightly);
+        assert_eq!(Channel::from_spec("rc/20"), Channel::Rc);
+        assert_eq!(
+            Channel::from_spec("v21.0.0-v8-canary2023"),
+            Channel::V8Canary
+        );
+        assert_eq!(Channel::from_spec("lts"), Channel::Release);
+        assert_eq!(Channel::from_spec("18.17"), Channel::Release);
+        assert_eq!(Channel::from_spec("18 - 20"), Channel::Release);
+        assert_eq!(Channel::from_spec("my-build"), Channel::Release);
+    }
+
+    #[test]
+    fn test_required_glibc() {
+        assert_eq!(Version::new(20, 0, 0).required_glibc(), Some((2, 28)));
+        assert_eq!(Version::new(18, 0, 0).required_glibc(), Some((2, 28)));
+        assert_eq!(Version::new(16, 20, 2).required_glibc(), Some((2, 17)));
+        assert_eq!(VERSION.required_glibc(), None);
+    }
+
+    #[test]
+    fn test_nearest_release() {
+        let release = |version: &str, files: Vec<File>| NodeIndexElement {
+            version: version.to_string(),
+            files,
+            ..Default::default()
+        };
+
+        let index = vec![
+            release("v1.2.5", vec![File::LinuxS390X]),
+            release("v1.2.4", vec![File::LinuxX64]),
+            release("v1.2.3", vec![File::LinuxX64]),
+            release("v1.2.2", vec![File::LinuxS390X]),
+        ];
+
+        let s390x = |i: &NodeIndexElement| i.files.contains(&File::LinuxS390X);
+
+        assert_eq!(find_release(&index, "1.2.3"), Some(&index[2]));
+        assert_eq!(find_release(&index, "v1"), Some(&index[0]));
+        assert_eq!(find_release(&index, "latest"), Some(&index[0]));
+        assert_eq!(find_release(&index, "v2"), None);
+
+        let prereleases = vec![
+            release("v1.3.0-rc.2", vec![]),
+            release("v1.3.0-rc.1", vec![]),
+            release("v1.2.5", vec![]),
+        ];
+
+        assert_eq!(find_release(&prereleases, "latest"), Some(&prereleases[2]));
+        assert_eq!(find_release(&prereleases, "rc"), Some(&prereleases[0]));
+        assert_eq!(
+            find_release(&prereleases, "v1.3.0-rc.1"),
+            Some(&prereleases[1])
+        );
+        assert_eq!(find_release(&prereleases, "1.3"), None);
+
+        assert_eq!(nearest_release(&index, &index[2], s390x), Some(&index[3]));
+        assert_eq!(nearest_release(&index, &index[1], s390x), Some(&index[0]));
+        assert_eq!(
+            nearest_release(&index, &index[0], |i| i.files.is_empty()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pick_archive() {
+        let mut installer = Installer::new(VERSION, Arch::X64, Libc::Glibc);
+        let name = installer.parse_installer_no_ext();
+
+        let shasums = vec![
+            ("gz".to_string(), format!("{}.tar.gz", name)),
+            ("xz-musl".to_string(), format!("{}-musl.tar.xz", name)),
+            ("pkg".to_string(), format!("{}.pkg", name)),
+        ];
+
+        installer
+            .pick_archive(&shasums, &[ArchiveType::TarXz, ArchiveType::TarGz])
+            .unwrap();
+
+        assert_eq!(installer.archive_type, ArchiveType::TarGz);
+        assert_eq!(installer.sha256.as_deref(), Some("gz"));
+
+        assert!(installer
+            .pick_archive(&shasums, &[ArchiveType::TarXz, ArchiveType::Zip])
+            .is_err());
+    }
+
     #[test]
     fn test_installer() {
         let arch = Arch::new();
-        let installer = Installer::new(VERSION, arch);
+        let installer = Installer::new(VERSION, arch, Libc::Glibc);
 
         let (os, ext) = crate::consts::OS_STR;
 
@@ -338,4 +1358,310 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_from_installer_name() {
+        let arch = Arch::new();
+        let name = Installer::new(VERSION, arch, Libc::Glibc).parse_installer_no_ext();
+
+        let installer = Installer::from_installer_name(&name).unwrap();
+
+        assert_eq!(installer.parse_installer_no_ext(), name);
+
+        assert!(Installer::from_installer_name("node-v1.2.3").is_err());
+        assert!(Installer::from_installer_name("not-node-v1.2.3-linux-x64").is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_from_installer_name_libc() {
+        let musl = Installer::from_installer_name("node-v1.2.3-linux-x64-musl").unwrap();
+
+        assert_eq!(musl.libc, Libc::Musl);
+        assert_eq!(musl.parse_installer_no_ext(), "node-v1.2.3-linux-x64-musl");
+
+        let glibc_217 =
+            Installer::from_installer_name("node-v18.17.1-linux-x64-glibc-217").unwrap();
+
+        assert_eq!(glibc_217.libc, Libc::Glibc217);
+        assert_eq!(
+            glibc_217.parse_installer_no_ext(),
+            "node-v18.17.1-linux-x64-glibc-217"
+        );
+    }
+
+    #[test]
+    fn test_chunk_ranges() {
+        assert_eq!(chunk_ranges(10, 4), vec![(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(chunk_ranges(8, 4), vec![(0, 3), (4, 7)]);
+        assert_eq!(chunk_ranges(3, 4), vec![(0, 2)]);
+        assert_eq!(chunk_ranges(0, 4), vec![]);
+    }
+
+    #[test]
+    fn test_verify_sha256() {
+        let hash = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+        assert!(verify_sha256(b"hello", hash).is_ok());
+        assert!(verify_sha256(b"hello", &hash.to_uppercase()).is_ok());
+        assert!(verify_sha256(b"goodbye", hash).is_err());
+    }
+
+    #[test]
+    fn test_untar_outside_path() {
+        let tar = |name: &str| {
+            let mut header = tar::Header::new_gnu();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(0);
+            header.set_mode(0o644);
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_cksum();
+
+            let mut builder = tar::Builder::new(Vec::new());
+            builder.append(&header, std::io::empty()).unwrap();
+            builder.into_inner().unwrap()
+        };
+
+        let untar = |bytes: Vec<u8>| {
+            Decompressor::untar_into_mem(&bytes[..], PathBuf::from("/data"), ProgressBar::hidden())
+        };
+
+        let archive = untar(tar("node-v20.9.0-linux-x64/bin/node")).unwrap();
+        assert_eq!(
+            archive.files[0].0,
+            PathBuf::from("/data/node-v20.9.0-linux-x64/bin/node")
+        );
+
+        assert!(matches!(
+            untar(tar("node-v20.9.0-linux-x64/../../../x")),
+            Err(InstallError::InvalidArchive)
+        ));
+        assert!(matches!(
+            untar(tar("/etc/x")),
+            Err(InstallError::InvalidArchive)
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_extract_twice() {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "node-v20.9.0-linux-x64/bin/", std::io::empty())
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "node-v20.9.0-linux-x64/bin/node", &b"node"[..])
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder
+            .append_link(&mut header, "node-v20.9.0-linux-x64/bin/npm", "node")
+            .unwrap();
+
+        let tar = builder.into_inner().unwrap();
+        let dir = TempDir::new("extract-twice");
+
+        for _ in 0..2 {
+            Decompressor::untar_into_mem(&tar[..], dir.to_path_buf(), ProgressBar::hidden())
+                .unwrap()
+                .decompress_with(ProgressBar::hidden())
+                .unwrap();
+        }
+
+        let bin = dir.join("node-v20.9.0-linux-x64").join("bin");
+        assert_eq!(
+            std::fs::read_link(bin.join("npm")).unwrap(),
+            Path::new("node")
+        );
+        assert_eq!(std::fs::read(bin.join("npm")).unwrap(), b"node");
+    }
+
+    #[test]
+    fn test_unzip() {
+        use std::io::Write;
+
+        let zip = |entries: &[(&str, u32)]| {
+            let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+
+            for (name, mode) in entries {
+                let options = zip::write::FileOptions::default().unix_permissions(*mode);
+
+                if name.ends_with('/') {
+                    writer.add_directory(*name, options).unwrap();
+                } else {
+                    writer.start_file(*name, options).unwrap();
+                    writer.write_all(name.as_bytes()).unwrap();
+                }
+            }
+
+            writer.finish().unwrap().into_inner()
+        };
+
+        let bytes = zip(&[
+            ("node-v20.9.0-win-x64/", 0o755),
+            ("node-v20.9.0-win-x64/node.exe", 0o755),
+            ("node-v20.9.0-win-x64/npm.cmd", 0o644),
+        ]);
+
+        let archive = Decompressor::unzip_into_mem(
+            Cursor::new(bytes.clone()),
+            PathBuf::from("/data"),
+            ProgressBar::hidden(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            archive.dirs,
+            vec![PathBuf::from("/data/node-v20.9.0-win-x64")]
+        );
+        assert_eq!(
+            archive
+                .files
+                .iter()
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("/data/node-v20.9.0-win-x64/node.exe"),
+                PathBuf::from("/data/node-v20.9.0-win-x64/npm.cmd"),
+            ]
+        );
+        assert_eq!(
+            archive
+                .modes
+                .iter()
+                .map(|(_, mode)| mode & 0o777)
+                .collect::<Vec<_>>(),
+            vec![0o755, 0o644]
+        );
+
+        // Windows archives, such as those fetched for another OS, are extracted on any OS
+        let dir = TempDir::new("unzip");
+        let version = InstalledVersion::new(VERSION, Arch::X64, Libc::Glibc);
+        NodeBinary::new(bytes, version, ArchiveType::Zip)
+            .extract(dir.to_path_buf())
+            .unwrap();
+
+        let node = dir.join("node-v20.9.0-win-x64").join("node.exe");
+        assert_eq!(
+            std::fs::read_to_string(&node).unwrap(),
+            "node-v20.9.0-win-x64/node.exe"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            assert_eq!(
+                std::fs::metadata(&node).unwrap().permissions().mode() & 0o777,
+                0o755
+            );
+        }
+
+        assert!(matches!(
+            Decompressor::unzip_into_mem(
+                Cursor::new(zip(&[("../x", 0o644)])),
+                PathBuf::from("/data"),
+                ProgressBar::hidden(),
+            ),
+            Err(InstallError::InvalidArchive)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_download_many() {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::fast(),
+        ));
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "node-v1.0.0-linux-x64/bin/", std::io::empty())
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "node-v1.0.0-linux-x64/bin/node", &b"node"[..])
+            .unwrap();
+
+        let tarball = builder.into_inner().unwrap().finish().unwrap();
+        let hash = format!("{:x}", Sha256::digest(&tarball));
+
+        let element = |version: &str| {
+            format!(
+                r#"{{"version":"{}","date":"2024-01-01","files":["linux-x64"],"v8":"1.0","lts":false,"security":false}}"#,
+                version
+            )
+        };
+
+        let mirror = serve(vec![
+            (
+                "/index.json".to_string(),
+                format!("[{},{}]", element("v2.0.0"), element("v1.0.0")).into_bytes(),
+            ),
+            (
+                "/v1.0.0/SHASUMS256.txt".to_string(),
+                format!("{}  node-v1.0.0-linux-x64.tar.gz\n", hash).into_bytes(),
+            ),
+            (
+                "/v2.0.0/SHASUMS256.txt".to_string(),
+                b"0000  node-v2.0.0-linux-x64.tar.gz\n".to_vec(),
+            ),
+            ("/v1.0.0/node-v1.0.0-linux-x64.tar.gz".to_string(), tarball),
+        ]);
+
+        let options = InstallOptions {
+            os: Os::Linux,
+            arch: Arch::X64,
+            libc: Libc::Glibc,
+            archive_types: vec![ArchiveType::TarGz],
+            mirror: Some(mirror),
+            download: DownloadOptions {
+                connections: 1,
+                ..DownloadOptions::DEFAULT
+            },
+            force: true,
+        };
+
+        let v1 = InstalledVersion::new(Version::new(1, 0, 0), Arch::X64, Libc::Glibc);
+        let v2 = InstalledVersion::new(Version::new(2, 0, 0), Arch::X64, Libc::Glibc);
+
+        let dir = TempDir::new("download-many");
+        let specs = ["1", "2", "1.0", "3"].map(String::from);
+
+        let results = download_many(&specs, &options, 2, &dir, std::slice::from_ref(&v2))
+            .await
+            .unwrap();
+
+        // 1.0 resolves to the same release as 1, so it is only installed once
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].0, "1");
+        assert_eq!(results[0].1.as_ref().unwrap(), &Installed::New(v1));
+        assert_eq!(results[1].0, "2");
+        assert_eq!(results[1].1.as_ref().unwrap(), &Installed::Existing(v2));
+        assert!(matches!(
+            &results[2],
+            (spec, Err(InstallError::VersionNotFound(_))) if spec == "3"
+        ));
+
+        assert!(dir.join("node-v1.0.0-linux-x64/bin/node").is_file());
+        assert!(!dir.join("node-v2.0.0-linux-x64").exists());
+    }
 }
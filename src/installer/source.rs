@@ -0,0 +1,355 @@
+use std::{
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use super::{
+    download, find_release, link_current, verify_sha256, ArchiveType, Decompressor, InstallError,
+    InstallOptions, Installer,
+};
+use crate::{
+    config::{Config, Current},
+    init_pb,
+    versions::{
+        index::{list_index, list_shasums, File},
+        Arch, Channel, InstalledVersion, Libc, Version,
+    },
+};
+
+/// Options controlling how NodeJS is built from source
+pub struct BuildOptions {
+    /// The number of jobs `make` runs in parallel
+    pub jobs: usize,
+    /// Extra flags passed to the configure script
+    pub configure_flags: Vec<String>,
+}
+
+impl BuildOptions {
+    /// Defaults to one job per available CPU
+    pub fn new(jobs: Option<usize>, configure_flags: Vec<String>) -> Self {
+        Self {
+            jobs: jobs.unwrap_or_else(|| {
+                std::thread::available_parallelism().map_or(1, |jobs| jobs.get())
+            }),
+            configure_flags,
+        }
+    }
+}
+
+/// The source tarball of a release, which is built for the host
+pub struct SourceRelease {
+    installer: Installer,
+    url: String,
+    file_name: String,
+    archive_type: ArchiveType,
+    sha256: String,
+}
+
+impl SourceRelease {
+    /// Finds the newest release matching the spec that publishes its source
+    pub async fn from_spec(spec: &str, options: &InstallOptions) -> Result<Self, InstallError> {
+        if cfg!(windows) {
+            return Err(InstallError::SourceUnsupported);
+        }
+
+        // The build uses the host's compiler and libc, so it cannot target another platform
+        let (arch, libc) = (Arch::detect(), Libc::detect());
+
+        if (options.arch, options.libc) != (arch, libc) {
+            return Err(InstallError::SourceNotHost {
+                platform: format!("{}{}", options.arch, options.libc.suffix()),
+                host: format!("{}{}", arch, libc.suffix()),
+            });
+        }
+
+        // The same source builds against any libc, so it is only fetched from the official mirrors
+        let dist_url = InstallOptions {
+            libc: Libc::Glibc,
+            ..options.clone()
+        }
+        .dist_url(Channel::from_spec(spec));
+
+        let index = list_index(&dist_url).await?;
+
+        let element = find_release(&index, spec)
+            .ok_or_else(|| InstallError::VersionNotFound(spec.to_string()))?;
+
+        if !element.files.contains(&File::Src) {
+            return Err(InstallError::SourceNotPublished(element.version.clone()));
+        }
+
+        let version: Version = element.version.parse()?;
+        let installer = Installer::new(version, options.arch, options.libc);
+
+        let shasums = list_shasums(&dist_url, &element.version).await?;
+        let name = format!("node-{}", element.version);
+
+        let (archive_type, file_name, sha256) = options
+            .archive_types
+            .iter()
+            .filter(|archive_type| **archive_type != ArchiveType::Zip)
+            .find_map(|archive_type| {
+                let file_name = format!("{}.{}", name, archive_type);
+
+                shasums
+                    .iter()
+                    .find(|(_, published)| published == &file_name)
+                    .map(|(hash, _)| (*archive_type, file_name, hash.clone()))
+            })
+            .ok_or_else(|| InstallError::SourceNotPublished(element.version.clone()))?;
+
+        Ok(Self {
+            url: format!("{}/{}/{}", dist_url, element.version, file_name),
+            installer,
+            file_name,
+            archive_type,
+            sha256,
+        })
+    }
+
+    /// The file that the output of the build is written to
+    pub fn log_path(&self) -> Result<PathBuf, InstallError> {
+        Ok(self.log_path_in(&Config::data_path()?))
+    }
+
+    fn log_path_in(&self, data_path: &Path) -> PathBuf {
+        data_path
+            .join("logs")
+            .join(format!("{}.log", self.installer.parse_installer_no_ext()))
+    }
+
+    /// Downloads, builds and installs the release into its normal version directory, and switches to it
+    pub async fn install(self, options: &BuildOptions) -> Result<InstalledVersion, InstallError> {
+        self.build_into(&Config::data_path()?, options).await?;
+
+        let installed = self.installer.installed();
+        let mut config = crate::consts::CONFIG.lock();
+
+        if !config.versions.contains(&installed) {
+            config.versions.push(installed.clone());
+        }
+
+        link_current(&mut config, Current::Installed(installed.clone()))?;
+
+        Ok(installed)
+    }
+
+    /// Downloads and builds the release into its version directory inside the data directory, without registering it
+    async fn build_into(
+        &self,
+        data_path: &Path,
+        options: &BuildOptions,
+    ) -> Result<(), InstallError> {
+        let bytes = download(
+            &self.url,
+            init_pb!(0, format!("Downloading {}", self.file_name)),
+        )
+        .await?;
+        verify_sha256(&bytes, &self.sha256)?;
+
+        let build_path = data_path.join("build");
+        let source_path = build_path.join(format!("node-{}", self.installer.version));
+
+        if source_path.exists() {
+            std::fs::remove_dir_all(&source_path)?;
+        }
+
+        Decompressor::new(Cursor::new(bytes), self.archive_type)
+            .decompress_into_mem(build_path)?
+            .decompress()?;
+
+        let install_path = data_path.join(self.installer.parse_installer_no_ext());
+
+        build(
+            &source_path,
+            &install_path,
+            options,
+            &self.log_path_in(data_path),
+        )?;
+
+        std::fs::remove_dir_all(&source_path)?;
+
+        Ok(())
+    }
+}
+
+/// Runs the configure script, `make` and `make install` in the source directory, installing into the prefix
+///
+/// The output of every step is streamed to the log file, which is replaced if it exists
+pub fn build(
+    source_path: &Path,
+    prefix: &Path,
+    options: &BuildOptions,
+    log_path: &Path,
+) -> Result<(), InstallError> {
+    if let Some(parent) = log_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut log = std::fs::File::create(log_path)?;
+
+    let mut configure = Command::new(source_path.join("configure"));
+    configure
+        .arg(format!("--prefix={}", prefix.display()))
+        .args(&options.configure_flags);
+
+    let mut make = Command::new("make");
+    make.arg(format!("-j{}", options.jobs));
+
+    let mut install = Command::new("make");
+    install.arg("install");
+
+    let mut steps = [
+        ("Configuring", configure),
+        ("Building", make),
+        ("Installing", install),
+    ];
+
+    let pb = init_pb!(steps.len() as u64);
+
+    for (step, command) in &mut steps {
+        pb.set_message(format!("{} NodeJS", step));
+
+        command
+            .current_dir(source_path)
+            .stdout(log.try_clone()?)
+            .stderr(log.try_clone()?);
+
+        writeln!(log, "$ {:?}", command)?;
+
+        let status = command.status()?;
+
+        if !status.success() {
+            return Err(InstallError::BuildFailed {
+                step: step.to_string(),
+                log: log_path.to_path_buf(),
+            });
+        }
+
+        pb.inc(1);
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::{
+        helpers::{serve, TempDir},
+        installer::DownloadOptions,
+    };
+
+    /// The configure script of a fake source tree, which writes a Makefile installing a stub binary
+    const CONFIGURE: &str = "#!/bin/sh\necho \"configured with $*\"\nprintf 'all:\\n\\techo built\\ninstall:\\n\\tmkdir -p %s/bin && touch %s/bin/node\\n' \"${1#--prefix=}\" \"${1#--prefix=}\" > Makefile\n";
+
+    #[test]
+    fn test_build() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("source");
+        let source_path = dir.join("node-v1.2.3");
+        let prefix = dir.join("node-v1.2.3-linux-x64");
+        let log_path = dir.join("logs").join("build.log");
+
+        std::fs::create_dir_all(&source_path).unwrap();
+
+        let configure = source_path.join("configure");
+        std::fs::write(&configure, CONFIGURE).unwrap();
+        std::fs::set_permissions(&configure, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let options = BuildOptions::new(Some(2), vec!["--shared".to_string()]);
+
+        build(&source_path, &prefix, &options, &log_path).unwrap();
+
+        assert!(prefix.join("bin").join("node").exists());
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        assert!(log.contains("--shared"));
+        assert!(log.contains("built"));
+
+        std::fs::write(&configure, "#!/bin/sh\nexit 1\n").unwrap();
+
+        assert!(matches!(
+            build(&source_path, &prefix, &options, &log_path),
+            Err(InstallError::BuildFailed { step, .. }) if step == "Configuring"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_install_from_mirror() {
+        let mut tarball = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::fast(),
+        ));
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        header.set_cksum();
+        tarball
+            .append_data(&mut header, "node-v1.2.3/", std::io::empty())
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(CONFIGURE.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        tarball
+            .append_data(&mut header, "node-v1.2.3/configure", CONFIGURE.as_bytes())
+            .unwrap();
+
+        let tarball = tarball.into_inner().unwrap().finish().unwrap();
+        let shasums = format!("{:x}  node-v1.2.3.tar.gz\n", Sha256::digest(&tarball));
+        let index = r#"[{"version":"v1.2.3","date":"2024-01-01","files":["src"],"v8":"1.0","lts":false,"security":false}]"#;
+
+        let mirror = serve(vec![
+            ("/index.json".to_string(), index.as_bytes().to_vec()),
+            ("/v1.2.3/SHASUMS256.txt".to_string(), shasums.into_bytes()),
+            ("/v1.2.3/node-v1.2.3.tar.gz".to_string(), tarball),
+        ]);
+
+        let options = InstallOptions {
+            os: crate::consts::OS_STR.0,
+            arch: Arch::detect(),
+            libc: Libc::detect(),
+            archive_types: vec![ArchiveType::TarXz, ArchiveType::TarGz],
+            mirror: Some(mirror),
+            download: DownloadOptions::DEFAULT,
+            force: false,
+        };
+
+        let release = SourceRelease::from_spec("1", &options).await.unwrap();
+
+        let dir = TempDir::new("source-mirror");
+        release
+            .build_into(&dir, &BuildOptions::new(Some(1), vec![]))
+            .await
+            .unwrap();
+
+        assert!(dir
+            .join(release.installer.parse_installer_no_ext())
+            .join("bin")
+            .join("node")
+            .exists());
+        assert!(!dir.join("build").join("node-v1.2.3").exists());
+
+        let foreign = InstallOptions {
+            arch: if Arch::detect() == Arch::X64 {
+                Arch::ARM64
+            } else {
+                Arch::X64
+            },
+            ..options
+        };
+
+        assert!(matches!(
+            SourceRelease::from_spec("1", &foreign).await,
+            Err(InstallError::SourceNotHost { .. })
+        ));
+    }
+}
@@ -43,3 +43,28 @@ pub fn symlink_dir(original: PathBuf, target: PathBuf) -> io::Result<()> {
 
     Ok(())
 }
+
+/// Removes a directory created by [`symlink_dir`], without touching the directory it points to
+pub fn remove_symlink_dir(target: PathBuf) -> io::Result<()> {
+    if std::fs::symlink_metadata(&target).is_err() {
+        return Ok(());
+    }
+
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            std::fs::remove_dir(target)?;
+        } else {
+            // Only contains symlinks, which `remove_dir_all` removes without following
+            std::fs::remove_dir_all(target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces any existing links at the target with links to the original
+pub fn replace_symlink_dir(original: PathBuf, target: PathBuf) -> io::Result<()> {
+    remove_symlink_dir(target.clone())?;
+
+    symlink_dir(original, target)
+}
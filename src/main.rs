@@ -1,15 +1,30 @@
+use std::path::PathBuf;
+
 use clap::{IntoApp, Parser};
 
-use config::Config;
-use installer::Installer;
+use config::{Config, Current};
+use installer::{
+    install_many, link_current, source::SourceRelease, uninstall, unlink_current, Installed,
+    Installer,
+};
+use system::SystemNode;
 
 mod args;
 mod config;
 mod consts;
+mod exec;
+mod export;
 mod helpers;
 mod installer;
 mod links;
+mod pin;
+mod project;
+mod session;
+mod shell;
+mod shim;
+mod system;
 mod versions;
+mod why;
 
 #[macro_use]
 extern crate tracing;
@@ -17,8 +32,17 @@ extern crate tracing;
 #[macro_use]
 mod macros;
 
+fn main() -> anyhow::Result<()> {
+    // Shims run on every invocation of node, so they skip the async runtime and global config
+    if let Some(name) = shim::invoked_as() {
+        return Ok(shim::run(&name)?);
+    }
+
+    run()
+}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn run() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
     let args = args::Args::parse();
@@ -40,9 +64,9 @@ async fn main() -> anyhow::Result<()> {
                 println!(
                 "No versions installed. Please run `yanvm install` to install a NodeJS version."
             );
-            } else if config.current == None {
+            } else if config.current.is_none() {
                 println!(
-                    "No current version set. Please run `yanvm set` to set a current version."
+                    "No current version set. Please run `yanvm use` to set a current version."
                 );
             } else {
                 args::Args::command().print_help()?;
@@ -51,17 +75,478 @@ async fn main() -> anyhow::Result<()> {
             return Ok(());
         }
         Some(command) => match command {
-            args::Commands::Install { version_str } => {
-                let version = match version_str.as_str() {
-                    "latest" => Installer::latest_version().await?,
-                    "lts" => Installer::lts_version().await?,
-                    _ => anyhow::bail!("Unexpected version string."),
+            args::Commands::Install {
+                version_strs,
+                parallel,
+                from_archive,
+                from_url,
+                sha256,
+                force,
+                from_source,
+                jobs,
+                configure_flags,
+            } => {
+                if let Some(path) = from_archive {
+                    let installer = Installer::install_archive(path, sha256.as_deref()).await?;
+                    println!("Installed {}", installer.parse_installer_no_ext());
+
+                    return Ok(());
+                }
+
+                if let Some(url) = from_url {
+                    let installer = Installer::install_url(&url, sha256.as_deref()).await?;
+                    println!("Installed {}", installer.parse_installer_no_ext());
+
+                    return Ok(());
+                }
+
+                let (options, build_options) = {
+                    let config = consts::CONFIG.lock();
+
+                    (
+                        config.install_options(args.arch, force),
+                        config.build_options(jobs, configure_flags),
+                    )
+                };
+
+                // Without a version, the one pinned by the project is installed, or else the latest LTS release
+                let version_strs = if version_strs.is_empty() {
+                    vec![version_or_pinned(None)?
+                        .map_or_else(|| "lts".to_string(), |(spec, _)| spec)]
+                } else {
+                    version_strs
+                };
+
+                if from_source {
+                    for version_str in &version_strs {
+                        let release = SourceRelease::from_spec(version_str, &options).await?;
+
+                        println!("Writing build logs to {}", release.log_path()?.display());
+
+                        let installed = release.install(&build_options).await?;
+                        println!("Installed {}", installed);
+                    }
+
+                    return Ok(());
+                }
+
+                let results = install_many(&version_strs, &options, parallel, true).await?;
+                let total = results.len();
+                let mut failed = 0;
+
+                for (version_str, result) in results {
+                    match result {
+                        Ok(Installed::New(version)) => println!("Installed {}", version),
+                        Ok(Installed::Existing(version)) => {
+                            println!("{} is already installed", version)
+                        }
+                        // A single failure is reported as is
+                        Err(e) if total == 1 => return Err(e.into()),
+                        Err(e) => {
+                            eprintln!("Failed to install {}: {}", version_str, e);
+                            failed += 1;
+                        }
+                    }
+                }
+
+                if failed > 0 {
+                    anyhow::bail!("Failed to install {} of {} versions", failed, total);
+                }
+            }
+            args::Commands::Link { name, path } => {
+                let mut config = consts::CONFIG.lock();
+
+                config.add_link(name.clone(), path)?;
+                config.save()?;
+
+                println!("Linked {}. Run `yanvm use {}` to switch to it.", name, name);
+            }
+            args::Commands::Use {
+                version_str,
+                install,
+                shell,
+                on_cd,
+            } => {
+                let (version_str, pinned_by) = match version_or_pinned(version_str)? {
+                    Some(found) => found,
+                    // Leaving a pinned project drops the shell's own version, so the current version applies again
+                    None if on_cd => {
+                        if shell {
+                            session::Session::from_env()?.unlink()?;
+                        }
+
+                        return Ok(());
+                    }
+                    None => anyhow::bail!(
+                        "No version given, and no .nvmrc, .node-version or .tool-versions file was found"
+                    ),
+                };
+
+                if version_str == "system" {
+                    // Entering a directory pinned to system drops the shell's own version, as system cannot be linked per shell
+                    if shell && on_cd {
+                        session::Session::from_env()?.unlink()?;
+
+                        return Ok(());
+                    }
+
+                    if shell {
+                        anyhow::bail!(
+                            "system cannot be used per shell, as the current version comes before it on the PATH"
+                        );
+                    }
+
+                    let mut config = consts::CONFIG.lock();
+
+                    let system = SystemNode::find(&config)
+                        .ok_or_else(|| anyhow::anyhow!("No system NodeJS installation found"))?;
+
+                    if on_cd && config.current.is_none() {
+                        return Ok(());
+                    }
+
+                    unlink_current(&mut config)?;
+
+                    println!("Now using {}", system);
+
+                    return Ok(());
+                }
+
+                let hint = format!("Run `yanvm install {}` to install it", version_str);
+                let version =
+                    resolve_or_install(version_str, pinned_by, args.arch, install, &hint).await?;
+
+                let mut config = consts::CONFIG.lock();
+
+                if shell {
+                    session::clean_stale()?;
+
+                    let session = session::Session::from_env()?;
+                    let bin_path = config.bin_path(&version)?;
+
+                    if on_cd && session.links_to(&bin_path) {
+                        return Ok(());
+                    }
+
+                    session.link(bin_path)?;
+
+                    println!("Now using {} in this shell", version);
+
+                    return Ok(());
+                }
+
+                if on_cd && config.current.as_ref() == Some(&version) {
+                    return Ok(());
+                }
+
+                link_current(&mut config, version.clone())?;
+
+                println!("Now using {}", version);
+            }
+            args::Commands::Exec {
+                version_str,
+                install,
+                command,
+            } => {
+                let (version_str, pinned_by) = match version_or_pinned(version_str)? {
+                    Some(found) => found,
+                    None => anyhow::bail!(
+                        "No version given, and no .nvmrc, .node-version or .tool-versions file was found"
+                    ),
+                };
+
+                let bin_path = if version_str == "system" {
+                    let system = SystemNode::find(&consts::CONFIG.lock())
+                        .ok_or_else(|| anyhow::anyhow!("No system NodeJS installation found"))?;
+
+                    system
+                        .path
+                        .parent()
+                        .map(|path| path.to_path_buf())
+                        .unwrap_or_default()
+                } else {
+                    let version = resolve_or_install(
+                        version_str,
+                        pinned_by,
+                        args.arch,
+                        install,
+                        "Pass --install to install it",
+                    )
+                    .await?;
+
+                    consts::CONFIG.lock().bin_path(&version)?
                 };
 
-                version.download_binary().await?.unzip_file().await?;
+                let path = exec::isolated_path(&consts::CONFIG.lock(), &bin_path)?;
+                let code = exec::exec(&command, path).await?;
+
+                std::process::exit(code);
+            }
+            args::Commands::Pin {
+                version_str,
+                file,
+                exact,
+            } => {
+                let config = consts::CONFIG.lock();
+
+                let version = match version_str {
+                    Some(version_str) => {
+                        config.resolve(&version_str, args.arch).ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "{} is not installed. Run `yanvm install {}` to install it",
+                                version_str,
+                                version_str
+                            )
+                        })?
+                    }
+                    None => config
+                        .current
+                        .clone()
+                        .ok_or_else(|| anyhow::anyhow!("No current version set to pin"))?,
+                };
+
+                let (path, file) = match file {
+                    Some(file) => (std::env::current_dir()?.join(file.file_name()), file),
+                    None => {
+                        let existing = project::find_current(&config)?.and_then(|project| {
+                            Some((
+                                project.path.clone(),
+                                pin::PinFile::from_path(&project.path)?,
+                            ))
+                        });
+
+                        match existing {
+                            Some(existing) => existing,
+                            None => (std::env::current_dir()?.join(".nvmrc"), pin::PinFile::Nvmrc),
+                        }
+                    }
+                };
+
+                let pinned = match &version {
+                    Current::Installed(installed) => pin::Pin::Version(&installed.version),
+                    Current::Linked(name) => pin::Pin::Linked(name),
+                };
+
+                let spec = pin::spec(&pinned, file, exact)?;
+                pin::write(&path, file, &spec)?;
+
+                println!("Pinned {} in {}", spec, path.display());
+            }
+            args::Commands::Why { version_str } => {
+                let trace = why::trace(&consts::CONFIG.lock(), version_str, args.arch)?;
+
+                println!("{}", trace);
+            }
+            args::Commands::Uninstall { version_str } => {
+                let mut config = consts::CONFIG.lock();
+
+                let version = config
+                    .resolve(&version_str, args.arch)
+                    .ok_or_else(|| anyhow::anyhow!("{} is not installed", version_str))?;
+
+                uninstall(&mut config, version.clone())?;
+
+                println!("Uninstalled {}", version);
+            }
+            args::Commands::Fetch {
+                version_str,
+                os,
+                out,
+            } => {
+                let options = consts::CONFIG
+                    .lock()
+                    .fetch_options(os.unwrap_or(consts::OS_STR.0), args.arch);
+
+                let version = Installer::from_spec(&version_str, &options).await?;
+
+                std::fs::create_dir_all(&out)?;
+                version.download_binary().await?.extract(out.clone())?;
+
+                println!(
+                    "Fetched {} into {}",
+                    version.parse_installer_no_ext(),
+                    out.join(version.parse_installer_no_ext()).display()
+                );
+            }
+            args::Commands::Export {
+                version_str,
+                format,
+                out,
+                prefix,
+            } => {
+                let config = consts::CONFIG.lock();
+
+                let version = config
+                    .resolve(&version_str, args.arch)
+                    .ok_or_else(|| anyhow::anyhow!("{} is not installed", version_str))?;
+
+                let install_path = config.install_path(&version)?;
+
+                match format {
+                    export::ExportFormat::Tar => {
+                        let default_prefix = install_path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+
+                        export::export_tar(
+                            &install_path,
+                            prefix.as_deref().unwrap_or(&default_prefix),
+                            &out,
+                        )?;
+                    }
+                    export::ExportFormat::Oci => {
+                        let (arch, ref_name) = match &version {
+                            Current::Installed(installed) => {
+                                (installed.arch, installed.version.to_string())
+                            }
+                            Current::Linked(name) => (config.default_arch(), name.clone()),
+                        };
+
+                        export::export_oci(
+                            &install_path,
+                            prefix.as_deref().unwrap_or("/usr/local"),
+                            (consts::OS_STR.0, arch),
+                            &ref_name,
+                            &out,
+                        )?;
+                    }
+                }
+
+                println!("Exported {} to {}", version, out.display());
+            }
+            args::Commands::Env {
+                shell,
+                use_on_cd,
+                install,
+            } => {
+                let shell = shell.map_or_else(shell::Shell::detect, Ok)?;
+
+                session::clean_stale()?;
+
+                let hook = use_on_cd
+                    .then(|| shell.use_on_cd_hook(install))
+                    .transpose()?;
+
+                println!("{}", shell.env_script()?);
+
+                if let Some(hook) = hook {
+                    println!("{}", hook);
+                }
+            }
+            args::Commands::Shims => {
+                let shims_path = shim::create()?;
+
+                println!(
+                    "Created shims for {} in {}",
+                    shim::SHIMS.join(", "),
+                    shims_path.display()
+                );
+                println!("Add it to the start of your PATH to use them");
+            }
+            args::Commands::List => {
+                let config = consts::CONFIG.lock();
+
+                let marker = |version: Current| {
+                    if config.current.as_ref() == Some(&version) {
+                        "*"
+                    } else {
+                        " "
+                    }
+                };
+
+                let mut versions = config.versions.clone();
+                versions.sort_by(|a, b| b.cmp(a));
+
+                // Prereleases are listed on their own, after everything else
+                let (prereleases, versions): (Vec<_>, Vec<_>) = versions
+                    .into_iter()
+                    .partition(|installed| installed.version.pre.is_some());
+
+                for installed in versions {
+                    println!(
+                        "{} {}",
+                        marker(Current::Installed(installed.clone())),
+                        installed
+                    );
+                }
+
+                for link in &config.links {
+                    println!(
+                        "{} {} -> {}",
+                        marker(Current::Linked(link.name.clone())),
+                        link.name,
+                        link.path.display()
+                    );
+                }
+
+                if let Some(system) = SystemNode::find(&config) {
+                    let marker = if config.current.is_none() { "*" } else { " " };
+
+                    println!("{} {}", marker, system);
+                }
+
+                if !prereleases.is_empty() {
+                    println!("\nPrereleases:");
+
+                    for installed in prereleases {
+                        println!(
+                            "{} {} [{}]",
+                            marker(Current::Installed(installed.clone())),
+                            installed,
+                            installed.version.channel()
+                        );
+                    }
+                }
             }
         },
     }
 
     Ok(())
 }
+
+/// The given version, or else the one pinned by the nearest version file along with the file's path
+fn version_or_pinned(
+    version_str: Option<String>,
+) -> anyhow::Result<Option<(String, Option<PathBuf>)>> {
+    Ok(match version_str {
+        Some(version_str) => Some((version_str, None)),
+        None => project::find_current(&consts::CONFIG.lock())?
+            .map(|project| (project.spec, Some(project.path))),
+    })
+}
+
+/// Resolves the spec to an installed or linked version, installing it first if asked to
+///
+/// The error for a missing version names the file that pinned it, followed by the hint
+async fn resolve_or_install(
+    version_str: String,
+    pinned_by: Option<PathBuf>,
+    arch: Option<versions::Arch>,
+    install: bool,
+    hint: &str,
+) -> anyhow::Result<Current> {
+    let resolved = consts::CONFIG.lock().resolve(&version_str, arch);
+
+    match resolved {
+        Some(version) => Ok(version),
+        None if install => {
+            let options = consts::CONFIG.lock().install_options(arch, false);
+
+            let (_, result) = install_many(&[version_str], &options, 1, false)
+                .await?
+                .remove(0);
+
+            Ok(Current::Installed(result?.into_version()))
+        }
+        None => match pinned_by {
+            Some(path) => anyhow::bail!(
+                "{} pinned by {} is not installed. {}",
+                version_str,
+                path.display(),
+                hint
+            ),
+            None => anyhow::bail!("{} is not installed. {}", version_str, hint),
+        },
+    }
+}
@@ -0,0 +1,417 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::versions::Version;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PinError {
+    #[error("Failed to interact with IO: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0} is not a valid package.json")]
+    InvalidPackageJson(PathBuf),
+    #[error("Only versions can be pinned in {0}, not linked names")]
+    NotAVersion(PinFile),
+}
+
+/// A file pinning the project's version
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ArgEnum)]
+pub enum PinFile {
+    Nvmrc,
+    NodeVersion,
+    ToolVersions,
+    /// The `engines.node` range of package.json
+    Engines,
+    /// Volta's `volta.node` pin in package.json, which is always exact
+    Volta,
+}
+
+impl PinFile {
+    pub const fn file_name(&self) -> &'static str {
+        match self {
+            PinFile::Nvmrc => ".nvmrc",
+            PinFile::NodeVersion => ".node-version",
+            PinFile::ToolVersions => ".tool-versions",
+            PinFile::Engines | PinFile::Volta => "package.json",
+        }
+    }
+
+    /// Recognizes a version file found in a project, by its name and for package.json by the field it pins
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.file_name()?.to_str()? {
+            ".nvmrc" => Some(PinFile::Nvmrc),
+            ".node-version" => Some(PinFile::NodeVersion),
+            ".tool-versions" => Some(PinFile::ToolVersions),
+            "package.json" => {
+                let package = std::fs::read_to_string(path)
+                    .ok()
+                    .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok());
+
+                match package {
+                    Some(package) if package["volta"]["node"].is_string() => Some(PinFile::Volta),
+                    _ => Some(PinFile::Engines),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PinFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PinFile::Engines => write!(f, "package.json engines"),
+            PinFile::Volta => write!(f, "package.json volta"),
+            file => write!(f, "{}", file.file_name()),
+        }
+    }
+}
+
+/// What to pin, either an installed version or a linked name
+pub enum Pin<'a> {
+    Version(&'a Version),
+    Linked(&'a str),
+}
+
+/// The spec written for the pin, which is only the major version unless exact
+///
+/// Prereleases are always exact, as a major version would only match stable releases.
+/// Volta and .tool-versions pins are always exact too, as Volta and asdf only accept full versions
+pub fn spec(pin: &Pin, file: PinFile, exact: bool) -> Result<String, PinError> {
+    match pin {
+        Pin::Linked(_) if matches!(file, PinFile::Engines | PinFile::Volta) => {
+            Err(PinError::NotAVersion(file))
+        }
+        Pin::Linked(name) => Ok(name.to_string()),
+        Pin::Version(version)
+            if exact
+                || version.pre.is_some()
+                || matches!(file, PinFile::Volta | PinFile::ToolVersions) =>
+        {
+            Ok(version.to_string().trim_start_matches('v').to_string())
+        }
+        Pin::Version(version) => Ok(version.major.to_string()),
+    }
+}
+
+/// Writes the spec to the file, keeping the rest of .tool-versions and package.json files as they are
+pub fn write(path: &Path, file: PinFile, spec: &str) -> Result<(), PinError> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(existing) => Some(existing),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let content = match file {
+        PinFile::Nvmrc | PinFile::NodeVersion => format!("{}\n", spec),
+        PinFile::ToolVersions => set_tool_version(existing.as_deref().unwrap_or_default(), spec),
+        PinFile::Engines | PinFile::Volta => {
+            let field = if file == PinFile::Volta {
+                "volta"
+            } else {
+                "engines"
+            };
+
+            set_package_json_node(existing.as_deref().unwrap_or("{}\n"), field, spec)
+                .ok_or_else(|| PinError::InvalidPackageJson(path.to_path_buf()))?
+        }
+    };
+
+    std::fs::write(path, content)?;
+
+    Ok(())
+}
+
+/// Replaces the versions of the `nodejs` line, or else adds one
+fn set_tool_version(content: &str, spec: &str) -> String {
+    let mut found = false;
+
+    let mut lines = content
+        .lines()
+        .map(|line| {
+            let tool = line.split_whitespace().next();
+
+            match tool {
+                Some(tool @ ("nodejs" | "node")) if !found => {
+                    found = true;
+
+                    // Trailing comments are kept
+                    let comment = line.find('#').map(|i| &line[i..]);
+
+                    match comment {
+                        Some(comment) => format!("{} {} {}", tool, spec, comment),
+                        None => format!("{} {}", tool, spec),
+                    }
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if !found {
+        lines.push(format!("nodejs {}", spec));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Sets `node` in the object at the top level field of a package.json, editing the text in place to keep its formatting
+///
+/// Returns none if the content is not a JSON object
+fn set_package_json_node(content: &str, field: &str, spec: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(content)
+        .ok()?
+        .as_object()?;
+
+    let root = content.find('{')?;
+    let members = object_members(content, root)?;
+    let value = serde_json::to_string(spec).ok()?;
+
+    let mut content = content.to_string();
+
+    match members.iter().find(|(key, _)| key == field) {
+        Some((_, span)) if content[span.clone()].starts_with('{') => {
+            let object = span.start;
+
+            match object_members(&content, object)?
+                .into_iter()
+                .find(|(key, _)| key == "node")
+            {
+                Some((_, node)) => content.replace_range(node, &value),
+                None => insert_member(&mut content, object, "node", &value)?,
+            }
+        }
+        // Any other value is replaced by an object
+        Some((_, span)) => {
+            let indent = indent_of(&content, root);
+            let object = format!("{{{}\"node\": {}{}}}", indent.nested, value, indent.member);
+
+            content.replace_range(span.clone(), &object);
+        }
+        None => {
+            let indent = indent_of(&content, root);
+            let object = format!("{{{}\"node\": {}{}}}", indent.nested, value, indent.member);
+
+            insert_member(&mut content, root, field, &object)?;
+        }
+    }
+
+    Some(content)
+}
+
+/// The whitespace before the members of an object, before the members of objects nested in it, and before its closing brace
+struct Indent {
+    member: String,
+    nested: String,
+    closing: String,
+}
+
+/// Guesses the indentation of the object at the brace from the whitespace before its first member
+///
+/// An empty package.json is indented with two spaces, like npm does
+fn indent_of(content: &str, open: usize) -> Indent {
+    let rest = &content[open + 1..];
+    let member = &rest[..rest.len() - rest.trim_start().len()];
+    let empty = rest.trim_start().starts_with('}');
+
+    if empty {
+        let (member, closing) = if content.find('{') == Some(open) {
+            ("\n  ", "\n")
+        } else {
+            (" ", " ")
+        };
+
+        return Indent {
+            member: member.to_string(),
+            nested: format!("{}  ", member),
+            closing: closing.to_string(),
+        };
+    }
+
+    let unit = member
+        .rfind('\n')
+        .map_or("", |newline| &member[newline + 1..]);
+
+    Indent {
+        member: member.to_string(),
+        nested: format!("{}{}", member, unit),
+        closing: String::new(),
+    }
+}
+
+/// Adds a member at the end of the object at the brace, indented like its other members
+fn insert_member(content: &mut String, open: usize, key: &str, value: &str) -> Option<()> {
+    let indent = indent_of(content, open);
+    let member = format!("{}: {}", serde_json::to_string(key).ok()?, value);
+
+    match object_members(content, open)?.last() {
+        Some((_, last)) => content.insert_str(last.end, &format!(",{}{}", indent.member, member)),
+        None => {
+            let close = open + 1 + content[open + 1..].find('}')?;
+
+            content.replace_range(
+                open + 1..close,
+                &format!("{}{}{}", indent.member, member, indent.closing),
+            );
+        }
+    }
+
+    Some(())
+}
+
+/// The keys of the members of the JSON object at the brace, with the byte span of each value
+fn object_members(content: &str, open: usize) -> Option<Vec<(String, Range<usize>)>> {
+    let bytes = content.as_bytes();
+    let mut members = Vec::new();
+    let mut i = skip_whitespace(bytes, open + 1);
+
+    if bytes.get(i) == Some(&b'}') {
+        return Some(members);
+    }
+
+    loop {
+        let key_end = skip_value(bytes, i)?;
+        let key = serde_json::from_str::<String>(&content[i..key_end]).ok()?;
+
+        i = skip_whitespace(bytes, key_end);
+        if bytes.get(i) != Some(&b':') {
+            return None;
+        }
+
+        let value_start = skip_whitespace(bytes, i + 1);
+        let value_end = skip_value(bytes, value_start)?;
+        members.push((key, value_start..value_end));
+
+        i = skip_whitespace(bytes, value_end);
+        match bytes.get(i) {
+            Some(b',') => i = skip_whitespace(bytes, i + 1),
+            Some(b'}') => return Some(members),
+            _ => return None,
+        }
+    }
+}
+
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+        i += 1;
+    }
+
+    i
+}
+
+/// The index after the JSON value starting at the index
+fn skip_value(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, byte) in bytes.iter().enumerate().skip(start) {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => {
+                    in_string = false;
+
+                    if depth == 0 {
+                        return Some(i + 1);
+                    }
+                }
+                _ => {}
+            }
+
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' if depth > 0 => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            b',' | b'}' | b']' if depth == 0 => return Some(i),
+            byte if depth == 0 && byte.is_ascii_whitespace() => return Some(i),
+            _ => {}
+        }
+    }
+
+    (depth == 0 && !in_string).then_some(bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_package_json_node() {
+        let package = "{\n  \"name\": \"app\",\n  \"engines\": {\n    \"node\": \">=16\",\n    \"npm\": \"*\"\n  }\n}\n";
+
+        assert_eq!(
+            set_package_json_node(package, "engines", "20").unwrap(),
+            "{\n  \"name\": \"app\",\n  \"engines\": {\n    \"node\": \"20\",\n    \"npm\": \"*\"\n  }\n}\n"
+        );
+
+        assert_eq!(
+            set_package_json_node(package, "volta", "20.9.0").unwrap(),
+            "{\n  \"name\": \"app\",\n  \"engines\": {\n    \"node\": \">=16\",\n    \"npm\": \"*\"\n  },\n  \"volta\": {\n    \"node\": \"20.9.0\"\n  }\n}\n"
+        );
+
+        let package = "{\n\t\"engines\": { \"npm\": \"*\" }\n}";
+
+        assert_eq!(
+            set_package_json_node(package, "engines", "20").unwrap(),
+            "{\n\t\"engines\": { \"npm\": \"*\", \"node\": \"20\" }\n}"
+        );
+
+        assert_eq!(
+            set_package_json_node("{}\n", "engines", "20").unwrap(),
+            "{\n  \"engines\": {\n    \"node\": \"20\"\n  }\n}\n"
+        );
+        assert_eq!(
+            set_package_json_node("{\"engines\":{}}", "engines", "20").unwrap(),
+            "{\"engines\":{ \"node\": \"20\" }}"
+        );
+
+        assert!(set_package_json_node("[]", "engines", "20").is_none());
+    }
+
+    #[test]
+    fn test_set_tool_version() {
+        assert_eq!(
+            set_tool_version("python 3.12.0\nnodejs 18.17.1 16 # old\n", "20"),
+            "python 3.12.0\nnodejs 20 # old\n"
+        );
+        assert_eq!(
+            set_tool_version("python 3.12.0\n", "20"),
+            "python 3.12.0\nnodejs 20\n"
+        );
+    }
+
+    #[test]
+    fn test_spec() {
+        let version = Version::new(20, 9, 0);
+
+        assert_eq!(
+            spec(&Pin::Version(&version), PinFile::Nvmrc, false).unwrap(),
+            "20"
+        );
+        assert_eq!(
+            spec(&Pin::Version(&version), PinFile::Nvmrc, true).unwrap(),
+            "20.9.0"
+        );
+        assert_eq!(
+            spec(&Pin::Version(&version), PinFile::Volta, false).unwrap(),
+            "20.9.0"
+        );
+        assert_eq!(
+            spec(&Pin::Version(&version), PinFile::ToolVersions, false).unwrap(),
+            "20.9.0"
+        );
+        assert!(spec(&Pin::Linked("my-build"), PinFile::Engines, false).is_err());
+    }
+}
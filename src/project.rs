@@ -0,0 +1,299 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// Files pinning the NodeJS version of a project, in order of priority within a directory
+const VERSION_FILES: &[&str] = &[".nvmrc", ".node-version", ".tool-versions"];
+
+/// A version pinned by a file in a project
+pub struct ProjectVersion {
+    /// The spec to use, which is the first installed fallback when the pinned spec is not installed
+    pub spec: String,
+    /// The pinned spec as written in the file, before aliases such as `lts/hydrogen` are converted
+    pub written: String,
+    /// Versions to use instead, in order, when the pinned spec is not installed
+    pub fallbacks: Vec<String>,
+    /// The index of the fallback used in place of the pinned spec
+    pub fallback: Option<usize>,
+    pub path: PathBuf,
+}
+
+impl ProjectVersion {
+    /// Pins the first spec as written, falling back to the others
+    fn new(written: Vec<String>, convert: fn(&str) -> String, path: PathBuf) -> Option<Self> {
+        let (first, rest) = written.split_first()?;
+
+        Some(Self {
+            spec: convert(first),
+            written: first.clone(),
+            fallbacks: rest.iter().map(|spec| convert(spec)).collect(),
+            fallback: None,
+            path,
+        })
+    }
+}
+
+/// Finds the nearest version file from the working directory up to the configured boundary
+///
+/// When the file lists fallbacks, the first one that is installed is used in place of the spec, like asdf does
+pub fn find_current(config: &Config) -> std::io::Result<Option<ProjectVersion>> {
+    let mut project = find_version(
+        &std::env::current_dir()?,
+        config.version_file_boundary.as_deref(),
+        config.read_package_json,
+    );
+
+    if let Some(project) = &mut project {
+        let installed = |spec: &String| spec == "system" || config.resolve(spec, None).is_some();
+
+        if !installed(&project.spec) {
+            if let Some(i) = project.fallbacks.iter().position(installed) {
+                project.spec = project.fallbacks[i].clone();
+                project.fallback = Some(i);
+            }
+        }
+    }
+
+    Ok(project)
+}
+
+/// Finds the nearest version file in the directory or any of its parents
+///
+/// Directories are searched from the nearest, so a version file always takes priority over those in parent directories.
+/// Within a directory, .nvmrc comes first, then .node-version, .tool-versions, and finally package.json if enabled.
+/// The search stops after the boundary, if the directory is inside it, and at the filesystem root otherwise
+fn find_version(
+    dir: &Path,
+    boundary: Option<&Path>,
+    read_package_json: bool,
+) -> Option<ProjectVersion> {
+    let boundary = boundary.filter(|boundary| dir.starts_with(boundary));
+
+    for dir in dir.ancestors() {
+        let found = VERSION_FILES
+            .iter()
+            .find_map(|name| {
+                let path = dir.join(name);
+                let content = std::fs::read_to_string(&path).ok()?;
+
+                match *name {
+                    ".tool-versions" => {
+                        ProjectVersion::new(parse_tool_versions(&content), from_asdf_spec, path)
+                    }
+                    _ => ProjectVersion::new(
+                        parse_version_file(&content).into_iter().collect(),
+                        from_nvm_spec,
+                        path,
+                    ),
+                }
+            })
+            .or_else(|| {
+                let path = dir.join("package.json");
+                let spec = read_package_json
+                    .then(|| parse_package_json(&std::fs::read_to_string(&path).ok()?))??;
+
+                ProjectVersion::new(vec![spec], str::to_string, path)
+            });
+
+        if found.is_some() || Some(dir) == boundary {
+            return found;
+        }
+    }
+
+    None
+}
+
+/// Reads the version spec from the first line of a version file that is not empty or a comment
+fn parse_version_file(content: &str) -> Option<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// Reads the versions of the `nodejs` line of an asdf or mise .tool-versions file, the first being preferred
+fn parse_tool_versions(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| {
+            line.split('#')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+        })
+        .find(|words| matches!(words.first(), Some(&"nodejs" | &"node")))
+        .map(|words| {
+            words[1..]
+                .iter()
+                // Git refs and arbitrary paths cannot be pinned
+                .filter(|version| !version.starts_with("ref:") && !version.starts_with("path:"))
+                .map(|version| version.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads Volta's exact `volta.node` pin, or else the `engines.node` range, from a package.json
+fn parse_package_json(content: &str) -> Option<String> {
+    let package = serde_json::from_str::<serde_json::Value>(content).ok()?;
+
+    let spec = [&package["volta"]["node"], &package["engines"]["node"]]
+        .into_iter()
+        .filter_map(|spec| spec.as_str())
+        .map(str::trim)
+        .find(|spec| !spec.is_empty())?;
+
+    Some(spec.to_string())
+}
+
+/// Converts the aliases used by nvm into the matching spec
+///
+/// Anything else is kept, so versions and linked names are used as is
+fn from_nvm_spec(spec: &str) -> String {
+    match spec {
+        "node" | "stable" => "latest".to_string(),
+        "lts/*" => "lts".to_string(),
+        spec => match spec.strip_prefix("lts/").and_then(lts_major) {
+            Some(major) => major.to_string(),
+            None => spec.to_string(),
+        },
+    }
+}
+
+/// Converts the `lts-<codename>` versions of asdf, and otherwise the aliases used by nvm, into the matching spec
+fn from_asdf_spec(spec: &str) -> String {
+    match spec.strip_prefix("lts-").and_then(lts_major) {
+        Some(major) => major.to_string(),
+        None => from_nvm_spec(spec),
+    }
+}
+
+/// The major version of an LTS line by its codename
+///
+/// LTS lines are named alphabetically, starting from argon for v4, with every even major version
+fn lts_major(codename: &str) -> Option<u32> {
+    let first = codename.chars().next()?.to_ascii_lowercase();
+
+    if !first.is_ascii_lowercase() || !codename.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    Some(4 + 2 * (first as u32 - 'a' as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::TempDir;
+
+    #[test]
+    fn test_parse_version_file() {
+        assert_eq!(
+            parse_version_file("v18.17.1\n"),
+            Some("v18.17.1".to_string())
+        );
+        assert_eq!(
+            parse_version_file("# Pinned for CI\n\n  20 # the LTS line\n"),
+            Some("20".to_string())
+        );
+        assert_eq!(
+            parse_version_file("lts/hydrogen"),
+            Some("lts/hydrogen".to_string())
+        );
+        assert_eq!(parse_version_file("# nothing\n\n"), None);
+    }
+
+    #[test]
+    fn test_from_nvm_spec() {
+        assert_eq!(from_nvm_spec("lts/*"), "lts");
+        assert_eq!(from_nvm_spec("lts/hydrogen"), "18");
+        assert_eq!(from_nvm_spec("lts/Iron"), "20");
+        assert_eq!(from_nvm_spec("node"), "latest");
+        assert_eq!(from_nvm_spec("my-build"), "my-build");
+        assert_eq!(from_asdf_spec("lts-hydrogen"), "18");
+        assert_eq!(from_asdf_spec("lts"), "lts");
+    }
+
+    #[test]
+    fn test_parse_tool_versions() {
+        assert_eq!(
+            parse_tool_versions("python 3.12.0\nnodejs 20.9.0 18.17.1 system # fallbacks\n"),
+            vec!["20.9.0", "18.17.1", "system"]
+        );
+        assert_eq!(
+            parse_tool_versions("node lts-hydrogen ref:v1.0.2 lts"),
+            vec!["lts-hydrogen", "lts"]
+        );
+        assert!(parse_tool_versions("# nodejs 20\npython 3.12.0\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_package_json() {
+        assert_eq!(
+            parse_package_json(r#"{ "engines": { "node": ">=18 <21" } }"#),
+            Some(">=18 <21".to_string())
+        );
+        assert_eq!(
+            parse_package_json(
+                r#"{ "engines": { "node": ">=18" }, "volta": { "node": "20.9.0" } }"#
+            ),
+            Some("20.9.0".to_string())
+        );
+        assert_eq!(parse_package_json(r#"{ "name": "app" }"#), None);
+        assert_eq!(parse_package_json("not json"), None);
+    }
+
+    #[test]
+    fn test_find_version() {
+        let dir = TempDir::new("project");
+        let project = dir.join("project");
+        let nested = project.join("packages").join("app");
+
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.join(".nvmrc"), "16").unwrap();
+        std::fs::write(project.join(".node-version"), "18").unwrap();
+
+        let found = find_version(&nested, None, false).unwrap();
+        assert_eq!(found.spec, "18");
+        assert_eq!(found.path, project.join(".node-version"));
+
+        // .nvmrc takes priority within the same directory
+        std::fs::write(project.join(".nvmrc"), "20").unwrap();
+        assert_eq!(find_version(&nested, None, false).unwrap().spec, "20");
+
+        std::fs::write(project.join(".nvmrc"), "lts/hydrogen").unwrap();
+        let found = find_version(&nested, None, false).unwrap();
+        assert_eq!(found.spec, "18");
+        assert_eq!(found.written, "lts/hydrogen");
+
+        std::fs::remove_file(project.join(".nvmrc")).unwrap();
+        std::fs::remove_file(project.join(".node-version")).unwrap();
+
+        std::fs::write(project.join(".tool-versions"), "nodejs 22.1.0 20.9.0\n").unwrap();
+        let found = find_version(&nested, None, false).unwrap();
+        assert_eq!(found.spec, "22.1.0");
+        assert_eq!(found.fallbacks, vec!["20.9.0"]);
+        assert_eq!(found.fallback, None);
+        std::fs::remove_file(project.join(".tool-versions")).unwrap();
+
+        // A package.json only counts when enabled
+        std::fs::write(
+            project.join("package.json"),
+            r#"{ "engines": { "node": "^20" } }"#,
+        )
+        .unwrap();
+        assert_eq!(find_version(&nested, None, true).unwrap().spec, "^20");
+
+        assert_eq!(find_version(&nested, None, false).unwrap().spec, "16");
+        assert!(find_version(&nested, Some(&project), false).is_none());
+        // Boundaries outside of the directory are ignored
+        assert_eq!(
+            find_version(&nested, Some(Path::new("/nonexistent")), false)
+                .unwrap()
+                .spec,
+            "16"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config::{Config, ConfigError, Current},
+    links::{remove_symlink_dir, replace_symlink_dir},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("This shell has no yanvm session. Load the output of `yanvm env` in your shell profile first")]
+    NoSession,
+    #[error("{0}")]
+    Config(#[from] ConfigError),
+    #[error("Failed to interact with IO: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A link directory belonging to a single shell, which comes before `current` on the PATH
+///
+/// The directory is named after the PID of the shell, and is set up by the script printed by `yanvm env`
+pub struct Session {
+    path: PathBuf,
+}
+
+impl Session {
+    /// The environment variable holding the session directory of the shell
+    pub const VAR: &'static str = "YANVM_SESSION";
+
+    /// The session of the shell yanvm is run from
+    ///
+    /// Only directories named after a PID in the sessions directory are accepted, as the session is replaced when linking
+    pub fn from_env() -> Result<Self, SessionError> {
+        let path = PathBuf::from(std::env::var_os(Self::VAR).ok_or(SessionError::NoSession)?);
+
+        if !is_session_path(&path, &Config::sessions_path()?) {
+            return Err(SessionError::NoSession);
+        }
+
+        Ok(Self { path })
+    }
+
+    /// Points the session at the given bin directory
+    pub fn link(&self, bin_path: PathBuf) -> Result<(), SessionError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        replace_symlink_dir(bin_path, self.path.clone())?;
+
+        Ok(())
+    }
+
+    /// Removes the session's link, so the shell uses the current version again
+    pub fn unlink(&self) -> Result<(), SessionError> {
+        remove_symlink_dir(self.path.clone())?;
+
+        Ok(())
+    }
+
+    /// Checks whether the session already points at the given bin directory
+    pub fn links_to(&self, bin_path: &Path) -> bool {
+        let node = if cfg!(windows) { "node.exe" } else { "node" };
+
+        // On Windows the whole directory is a link, elsewhere every entry is
+        let (link, target) = if cfg!(windows) {
+            (self.path.clone(), bin_path.to_path_buf())
+        } else {
+            (self.path.join(node), bin_path.join(node))
+        };
+
+        std::fs::read_link(link).is_ok_and(|link| link == target)
+    }
+
+    /// The installed or linked version the session points at, if any
+    pub fn linked_version(&self, config: &Config) -> Option<Current> {
+        config
+            .versions
+            .iter()
+            .map(|installed| Current::Installed(installed.clone()))
+            .chain(
+                config
+                    .links
+                    .iter()
+                    .map(|link| Current::Linked(link.name.clone())),
+            )
+            .find(|version| {
+                config
+                    .bin_path(version)
+                    .is_ok_and(|bin_path| self.links_to(&bin_path))
+            })
+    }
+}
+
+fn is_session_path(path: &Path, sessions_path: &Path) -> bool {
+    path.parent() == Some(sessions_path)
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.parse::<u32>().is_ok())
+}
+
+/// Removes the session directories of shells that have exited
+pub fn clean_stale() -> Result<(), SessionError> {
+    let sessions_path = Config::sessions_path()?;
+
+    let entries = match std::fs::read_dir(&sessions_path) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+
+        let stale = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u32>().ok())
+            .is_some_and(|pid| !is_running(pid));
+
+        if stale {
+            remove_symlink_dir(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn is_running(pid: u32) -> bool {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "linux")] {
+            Path::new("/proc").join(pid.to_string()).exists()
+        } else if #[cfg(windows)] {
+            std::process::Command::new("tasklist")
+                .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+                .output()
+                .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+                .unwrap_or(true)
+        } else {
+            std::process::Command::new("kill")
+                .args(["-0", &pid.to_string()])
+                .stderr(std::process::Stdio::null())
+                .status()
+                .map(|status| status.success())
+                .unwrap_or(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_session_path() {
+        let sessions_path = Path::new("/run/user/1000/yanvm/sessions");
+
+        assert!(is_session_path(&sessions_path.join("4242"), sessions_path));
+        assert!(!is_session_path(Path::new("/home/user"), sessions_path));
+        assert!(!is_session_path(sessions_path, sessions_path));
+        assert!(!is_session_path(
+            &sessions_path.join("shell"),
+            sessions_path
+        ));
+        assert!(!is_session_path(
+            &sessions_path.join("4242").join("1"),
+            sessions_path
+        ));
+        assert!(!is_session_path(
+            &sessions_path.join("..").join("4242"),
+            sessions_path
+        ));
+    }
+}
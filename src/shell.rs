@@ -0,0 +1,253 @@
+use std::path::Path;
+
+use crate::{
+    config::{Config, ConfigError},
+    session::Session,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShellError {
+    #[error("Could not detect the current shell. Pass --shell to choose one")]
+    UnknownShell,
+    #[error("{0}")]
+    Config(#[from] ConfigError),
+    #[error("Switching versions on directory change is not supported in {0}")]
+    UseOnCdUnsupported(Shell),
+    #[error("Failed to interact with IO: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A shell that yanvm can print setup for
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ArgEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Nushell,
+    #[clap(alias = "pwsh")]
+    Powershell,
+}
+
+impl Shell {
+    /// Detects the shell yanvm was run from, by the parent process on Linux, and by the environment otherwise
+    pub fn detect() -> Result<Self, ShellError> {
+        #[cfg(target_os = "linux")]
+        {
+            let parent = std::os::unix::process::parent_id();
+
+            if let Some(shell) = std::fs::read_to_string(format!("/proc/{}/comm", parent))
+                .ok()
+                .and_then(|comm| Self::from_program(comm.trim()))
+            {
+                return Ok(shell);
+            }
+        }
+
+        if std::env::var_os("NU_VERSION").is_some() {
+            return Ok(Shell::Nushell);
+        }
+
+        if let Some(shell) = std::env::var("SHELL")
+            .ok()
+            .and_then(|shell| Self::from_program(&shell))
+        {
+            return Ok(shell);
+        }
+
+        if cfg!(windows) {
+            return Ok(Shell::Powershell);
+        }
+
+        Err(ShellError::UnknownShell)
+    }
+
+    /// Recognizes a shell by the name or path of its executable
+    fn from_program(program: &str) -> Option<Self> {
+        let name = Path::new(program).file_stem()?.to_string_lossy();
+
+        // Login shells are run with a leading dash, such as `-bash`
+        match name.trim_start_matches('-') {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "nu" => Some(Shell::Nushell),
+            "pwsh" | "powershell" => Some(Shell::Powershell),
+            _ => None,
+        }
+    }
+
+    /// Quotes a value so that the shell reads it literally
+    pub fn quote(&self, value: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("'{}'", value.replace('\'', r"'\''")),
+            Shell::Fish => format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'")),
+            Shell::Nushell => format!("r#'{}'#", value),
+            Shell::Powershell => format!("'{}'", value.replace('\'', "''")),
+        }
+    }
+
+    /// A line prepending the directory to a path list variable, such as `PATH`
+    pub fn prepend_path(&self, var: &str, dir: &Path) -> String {
+        self.prepend_path_expr(var, &self.quote(&dir.to_string_lossy()))
+    }
+
+    /// Like [`Shell::prepend_path`], with a directory given as an expression in the shell's language
+    fn prepend_path_expr(&self, var: &str, dir: &str) -> String {
+        match self {
+            // An empty entry in MANPATH keeps the default search path
+            Shell::Bash | Shell::Zsh => format!("export {var}={dir}:\"${{{var}-}}\""),
+            Shell::Fish => format!("set -q {var}; or set -gx {var} ''\nset -gx {var} {dir} ${var}"),
+            Shell::Nushell => format!(
+                "$env.{var} = ($env.{var}? | default [] | split row (char esep) | prepend {dir} | str join (char esep))"
+            ),
+            Shell::Powershell => format!(
+                "$env:{var} = {dir} + [IO.Path]::PathSeparator + $env:{var}"
+            ),
+        }
+    }
+
+    /// A line exporting the variable
+    pub fn set_var(&self, var: &str, value: &str) -> String {
+        let value = self.quote(value);
+
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export {}={}", var, value),
+            Shell::Fish => format!("set -gx {} {}", var, value),
+            Shell::Nushell => format!("$env.{} = {}", var, value),
+            Shell::Powershell => format!("$env:{} = {}", var, value),
+        }
+    }
+
+    /// A line pointing the session variable at a directory named after the shell's PID
+    fn set_session_var(&self, sessions_path: &Path) -> String {
+        let sessions_path = self.quote(&sessions_path.to_string_lossy());
+        let var = Session::VAR;
+
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export {}={}/\"$$\"", var, sessions_path),
+            Shell::Fish => format!("set -gx {} {}/$fish_pid", var, sessions_path),
+            Shell::Nushell => format!(
+                "$env.{} = ({} | path join ($nu.pid | into string))",
+                var, sessions_path
+            ),
+            Shell::Powershell => format!("$env:{} = Join-Path {} $PID", var, sessions_path),
+        }
+    }
+
+    /// A reference to the value of an environment variable
+    fn var_ref(&self, var: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("\"${}\"", var),
+            Shell::Fish => format!("${}", var),
+            Shell::Nushell => format!("$env.{}", var),
+            Shell::Powershell => format!("$env:{}", var),
+        }
+    }
+
+    /// The script setting up the environment, which puts the current version on the PATH
+    ///
+    /// The shell's session directory comes first, so versions used with `yanvm use --shell` take priority
+    pub fn env_script(&self) -> Result<String, ShellError> {
+        let mut lines = vec![
+            format!("# Load this in your shell profile with: {}", self.usage()),
+            self.set_var("YANVM_DIR", &Config::data_path()?.to_string_lossy()),
+            self.set_var("YANVM_SHELL", &self.to_string()),
+            self.set_session_var(&Config::sessions_path()?),
+            self.prepend_path("PATH", &Config::current_path()?),
+            self.prepend_path_expr("PATH", &self.var_ref(Session::VAR)),
+        ];
+
+        // NodeJS only ships man pages outside of Windows
+        if !cfg!(windows) {
+            lines.push(self.prepend_path("MANPATH", &Config::current_man_path()?));
+        }
+
+        Ok(lines.join("\n"))
+    }
+
+    /// A hook switching to the project's version whenever the directory changes, and once when loaded
+    ///
+    /// The hook only runs yanvm when the directory changed, which then only switches if the resolved version differs
+    pub fn use_on_cd_hook(&self, install: bool) -> Result<String, ShellError> {
+        let exe = self.quote(&std::env::current_exe()?.to_string_lossy());
+        // Switching only affects this shell
+        let command = format!(
+            "{} use --shell --on-cd{}",
+            exe,
+            if install { " --install" } else { "" }
+        );
+
+        let hook = match self {
+            Shell::Bash => format!(
+                r#"__yanvm_use_on_cd() {{
+  if [ "${{__YANVM_LAST_PWD-}}" != "$PWD" ]; then
+    __YANVM_LAST_PWD="$PWD"
+    {command}
+  fi
+}}
+PROMPT_COMMAND="__yanvm_use_on_cd${{PROMPT_COMMAND:+; $PROMPT_COMMAND}}"
+__yanvm_use_on_cd"#
+            ),
+            Shell::Zsh => format!(
+                r#"autoload -U add-zsh-hook
+__yanvm_use_on_cd() {{
+  {command}
+}}
+add-zsh-hook chpwd __yanvm_use_on_cd
+__yanvm_use_on_cd"#
+            ),
+            Shell::Fish => format!(
+                r#"function __yanvm_use_on_cd --on-variable PWD
+    {command}
+end
+__yanvm_use_on_cd"#
+            ),
+            shell => return Err(ShellError::UseOnCdUnsupported(*shell)),
+        };
+
+        Ok(hook)
+    }
+
+    /// How to load the output of `yanvm env` in this shell
+    const fn usage(&self) -> &'static str {
+        match self {
+            Shell::Bash => "eval \"$(yanvm env --shell bash)\"",
+            Shell::Zsh => "eval \"$(yanvm env --shell zsh)\"",
+            Shell::Fish => "yanvm env --shell fish | source",
+            Shell::Nushell => "yanvm env --shell nushell | save -f ~/.yanvm.nu; source ~/.yanvm.nu",
+            Shell::Powershell => "yanvm env --shell powershell | Out-String | Invoke-Expression",
+        }
+    }
+}
+
+impl std::fmt::Display for Shell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Shell::Bash => write!(f, "bash"),
+            Shell::Zsh => write!(f, "zsh"),
+            Shell::Fish => write!(f, "fish"),
+            Shell::Nushell => write!(f, "nushell"),
+            Shell::Powershell => write!(f, "powershell"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_program() {
+        assert_eq!(Shell::from_program("/usr/bin/zsh"), Some(Shell::Zsh));
+        assert_eq!(Shell::from_program("-bash"), Some(Shell::Bash));
+        assert_eq!(Shell::from_program("pwsh.exe"), Some(Shell::Powershell));
+        assert_eq!(Shell::from_program("yanvm"), None);
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(Shell::Bash.quote("it's"), r"'it'\''s'");
+        assert_eq!(Shell::Fish.quote(r"a\b'c"), r"'a\\b\'c'");
+        assert_eq!(Shell::Powershell.quote("it's"), "'it''s'");
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    config::{Config, ConfigError, Current},
+    project::{self, ProjectVersion},
+    system::SystemNode,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShimError {
+    #[error("{0}")]
+    Config(#[from] ConfigError),
+    #[error("Failed to interact with IO: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{spec}, set by {set_by}, is not installed. Run `yanvm install {spec}` to install it")]
+    NotInstalled { spec: String, set_by: String },
+    #[error("No {0} binary found. Run `yanvm use` to set a current version")]
+    NotFound(String),
+}
+
+/// The binaries that shims are created for
+pub const SHIMS: &[&str] = &["node", "npm", "npx", "corepack"];
+
+/// The environment variable overriding the version used by shims
+pub const VERSION_VAR: &str = "YANVM_VERSION";
+
+/// The shim yanvm was invoked as, if any
+pub fn invoked_as() -> Option<String> {
+    let arg0 = PathBuf::from(std::env::args_os().next()?);
+    let name = arg0.file_stem()?.to_str()?;
+
+    SHIMS.contains(&name).then(|| name.to_string())
+}
+
+/// Runs the real binary of the version resolved for the working directory, replacing this process where possible
+///
+/// This runs on every invocation of a shim, so it avoids the async runtime and the global config
+pub fn run(name: &str) -> Result<(), ShimError> {
+    let config = Config::read()?;
+
+    let bin_path = resolve_bin_path(
+        &config,
+        std::env::var(VERSION_VAR).ok(),
+        project::find_current(&config)?,
+    )?;
+
+    let bin = bin_names(name)
+        .iter()
+        .find_map(|bin_name| match &bin_path {
+            Some(bin_path) => Some(bin_path.join(bin_name)).filter(|bin| bin.is_file()),
+            None => SystemNode::find_bin(&config, bin_name),
+        })
+        .ok_or_else(|| ShimError::NotFound(name.to_string()))?;
+
+    // Batch scripts, such as npm.cmd, can only be run by cmd
+    let mut command = if bin.extension() == Some(OsStr::new("cmd")) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(&bin);
+        command
+    } else {
+        Command::new(&bin)
+    };
+    command.args(std::env::args_os().skip(1));
+
+    // Binaries run by the real one, such as `node` run by `npm`, skip the shims
+    if let Some(bin_dir) = bin.parent() {
+        command.env("PATH", prepend_path(bin_dir));
+    }
+
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            let status = command.status()?;
+
+            std::process::exit(status.code().unwrap_or(1));
+        } else {
+            use std::os::unix::process::CommandExt;
+
+            Err(command.exec().into())
+        }
+    }
+}
+
+/// Finds the bin directory of the version set by the environment variable, the nearest version file or the current version
+///
+/// Returns none when no version is set, in which case the system installation is used
+fn resolve_bin_path(
+    config: &Config,
+    env: Option<String>,
+    project: Option<ProjectVersion>,
+) -> Result<Option<PathBuf>, ShimError> {
+    let (spec, set_by) = match env {
+        Some(spec) => (spec, VERSION_VAR.to_string()),
+        None => match project {
+            Some(project) => (project.spec, project.path.display().to_string()),
+            None => {
+                return match &config.current {
+                    Some(current) => Ok(Some(config.bin_path(current)?)),
+                    None => Ok(None),
+                }
+            }
+        },
+    };
+
+    if spec == "system" {
+        return Ok(None);
+    }
+
+    let version: Current = config
+        .resolve(&spec, None)
+        .ok_or(ShimError::NotInstalled { spec, set_by })?;
+
+    Ok(Some(config.bin_path(&version)?))
+}
+
+/// The file names the binary may have, in order of preference
+///
+/// On Windows, NodeJS ships node as an executable, but npm, npx and corepack as batch scripts
+fn bin_names(name: &str) -> Vec<String> {
+    if cfg!(windows) {
+        vec![format!("{}.exe", name), format!("{}.cmd", name)]
+    } else {
+        vec![name.to_string()]
+    }
+}
+
+fn prepend_path(dir: &Path) -> OsString {
+    let paths = std::env::var_os("PATH").unwrap_or_default();
+
+    std::env::join_paths(std::iter::once(dir.to_path_buf()).chain(std::env::split_paths(&paths)))
+        .unwrap_or(paths)
+}
+
+/// Creates shims pointing at the yanvm binary, replacing any existing ones
+pub fn create() -> Result<PathBuf, ShimError> {
+    let shims_path = Config::shims_path()?;
+    let exe = std::env::current_exe()?;
+
+    std::fs::create_dir_all(&shims_path)?;
+
+    for name in SHIMS {
+        let shim = shims_path.join(if cfg!(windows) {
+            format!("{}.exe", name)
+        } else {
+            name.to_string()
+        });
+
+        if std::fs::symlink_metadata(&shim).is_ok() {
+            std::fs::remove_file(&shim)?;
+        }
+
+        cfg_if::cfg_if! {
+            if #[cfg(windows)] {
+                // Hard links keep the shim's own name as the program name, unlike symlinks on Windows
+                if std::fs::hard_link(&exe, &shim).is_err() {
+                    std::fs::copy(&exe, &shim)?;
+                }
+            } else {
+                std::os::unix::fs::symlink(&exe, &shim)?;
+            }
+        }
+    }
+
+    Ok(shims_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::LinkedVersion,
+        versions::{Arch, InstalledVersion, Libc, Version},
+    };
+
+    fn project(spec: &str) -> ProjectVersion {
+        ProjectVersion {
+            spec: spec.to_string(),
+            written: spec.to_string(),
+            fallbacks: Vec::new(),
+            fallback: None,
+            path: PathBuf::from("/project/.nvmrc"),
+        }
+    }
+
+    /// The bin directory resolved from linked versions under /opt, relative to /opt
+    fn resolved(
+        config: &Config,
+        env: Option<&str>,
+        project: Option<ProjectVersion>,
+    ) -> Option<PathBuf> {
+        resolve_bin_path(config, env.map(str::to_string), project)
+            .unwrap()
+            .map(|path| path.strip_prefix("/opt").unwrap().to_path_buf())
+    }
+
+    #[test]
+    fn test_resolve_bin_path() {
+        let mut config = Config::blank();
+        config.links = ["env", "project", "current"]
+            .iter()
+            .map(|name| LinkedVersion {
+                name: name.to_string(),
+                path: PathBuf::from("/opt").join(name),
+            })
+            .collect();
+
+        // Without any version set, the system installation is used
+        assert_eq!(resolved(&config, None, None), None);
+
+        config.current = Some(Current::Linked("current".to_string()));
+
+        assert_eq!(
+            resolved(&config, None, None),
+            Some(PathBuf::from("current/bin"))
+        );
+        assert_eq!(
+            resolved(&config, None, Some(project("project"))),
+            Some(PathBuf::from("project/bin"))
+        );
+        assert_eq!(
+            resolved(&config, Some("env"), Some(project("project"))),
+            Some(PathBuf::from("env/bin"))
+        );
+        assert_eq!(
+            resolved(&config, Some("system"), Some(project("project"))),
+            None
+        );
+        assert_eq!(resolved(&config, None, Some(project("system"))), None);
+    }
+
+    #[test]
+    fn test_resolve_bin_path_not_installed() {
+        let mut config = Config::blank();
+        config.arch = Some(Arch::X64);
+        config.libc = Some(Libc::Glibc);
+        config.versions = vec![InstalledVersion::new(
+            Version::new(20, 9, 0),
+            Arch::X64,
+            Libc::Glibc,
+        )];
+
+        assert!(matches!(
+            resolve_bin_path(&config, Some("18".to_string()), Some(project("20"))),
+            Err(ShimError::NotInstalled { spec, set_by }) if spec == "18" && set_by == VERSION_VAR
+        ));
+        assert!(matches!(
+            resolve_bin_path(&config, None, Some(project("18"))),
+            Err(ShimError::NotInstalled { set_by, .. }) if set_by == "/project/.nvmrc"
+        ));
+    }
+}
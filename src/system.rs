@@ -0,0 +1,109 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    config::{Config, ConfigError},
+    versions::Version,
+};
+
+#[cfg(windows)]
+const NODE_BIN: &str = "node.exe";
+#[cfg(not(windows))]
+const NODE_BIN: &str = "node";
+
+/// A NodeJS installation found on the PATH, which is not managed by yanvm
+pub struct SystemNode {
+    pub path: PathBuf,
+    pub version: Option<Version>,
+}
+
+impl SystemNode {
+    /// Searches the PATH for a `node` binary outside of yanvm's directories and linked versions
+    pub fn find(config: &Config) -> Option<Self> {
+        let path = Self::find_bin(config, NODE_BIN)?;
+
+        let version = Self::version_of(&path);
+
+        Some(Self { path, version })
+    }
+
+    /// Searches the PATH for a binary outside of yanvm's directories and linked versions
+    pub fn find_bin(config: &Config, name: &str) -> Option<PathBuf> {
+        let managed = managed_paths(config).unwrap_or_default();
+
+        let paths = std::env::var_os("PATH")?;
+
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(name))
+            .filter(|bin| bin.is_file())
+            .find(|bin| !is_managed(bin, &managed))
+    }
+
+    fn version_of(path: &Path) -> Option<Version> {
+        let output = Command::new(path).arg("--version").output().ok()?;
+
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    }
+}
+
+/// The directories managed by yanvm, which are its data and sessions directories and every linked version
+///
+/// Symlinked directories, such as the data directory itself, are included by their target too
+pub fn managed_paths(config: &Config) -> Result<Vec<PathBuf>, ConfigError> {
+    let mut managed = vec![Config::data_path()?, Config::sessions_path()?];
+    managed.extend(config.links.iter().map(|link| link.path.clone()));
+
+    let canonical = managed
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .collect::<Vec<_>>();
+    managed.extend(canonical);
+
+    Ok(managed)
+}
+
+/// Whether the path is inside any of the managed directories
+///
+/// Symlinks are resolved, so that the `current` directory and session links are recognized as managed
+pub fn is_managed(path: &Path, managed: &[PathBuf]) -> bool {
+    let resolved = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    managed
+        .iter()
+        .any(|managed| path.starts_with(managed) || resolved.starts_with(managed))
+}
+
+impl std::fmt::Display for SystemNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "system ({}) -> {}", version, self.path.display()),
+            None => write!(f, "system (unknown version) -> {}", self.path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_managed() {
+        let managed = vec![PathBuf::from("/home/user/.local/share/yanvm")];
+
+        assert!(is_managed(
+            Path::new("/home/user/.local/share/yanvm/current/bin"),
+            &managed
+        ));
+        assert!(is_managed(
+            Path::new("/home/user/.local/share/yanvm/shims"),
+            &managed
+        ));
+        assert!(!is_managed(Path::new("/usr/local/bin"), &managed));
+        assert!(!is_managed(
+            Path::new("/home/user/.local/share/yanvm-other"),
+            &managed
+        ));
+    }
+}
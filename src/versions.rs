@@ -1,13 +1,82 @@
+use std::{cmp::Ordering, str::FromStr};
+
 use serde::{Deserialize, Serialize};
 
+use crate::installer::ArchiveType;
+
 pub mod index;
+pub mod range;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VersionError {
+    #[error("Unsupported architecture: {0}")]
+    UnknownArch(String),
+    #[error("Invalid version string: {0}")]
+    InvalidVersion(String),
+    #[error("Invalid version range: {0}")]
+    InvalidRange(String),
+    #[error("Unsupported operating system: {0}")]
+    UnknownOs(String),
+}
+
+/// An operating system that NodeJS publishes builds for
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Os {
+    Win,
+    Darwin,
+    Linux,
+}
+
+impl Os {
+    /// The archive format NodeJS has published for this OS the longest
+    pub const fn archive_type(&self) -> ArchiveType {
+        match self {
+            Os::Win => ArchiveType::Zip,
+            Os::Darwin => ArchiveType::TarGz,
+            Os::Linux => ArchiveType::TarXz,
+        }
+    }
+}
+
+impl FromStr for Os {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "win" | "windows" => Ok(Os::Win),
+            "darwin" | "macos" | "osx" => Ok(Os::Darwin),
+            "linux" => Ok(Os::Linux),
+            _ => Err(VersionError::UnknownOs(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Os {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Os::Win => write!(f, "win"),
+            Os::Darwin => write!(f, "darwin"),
+            Os::Linux => write!(f, "linux"),
+        }
+    }
+}
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub enum Arch {
+    #[serde(rename = "x86")]
     X86,
+    #[serde(rename = "x64")]
     X64,
+    #[serde(rename = "armv7l")]
     ARMv7,
+    #[serde(rename = "arm64")]
     ARM64,
+    #[serde(rename = "armv6l")]
+    ARMv6,
+    #[serde(rename = "ppc64le")]
+    PPC64LE,
+    #[serde(rename = "s390x")]
+    S390X,
 }
 
 impl Arch {
@@ -20,12 +89,92 @@ impl Arch {
             Arch::ARMv7
         } else if cfg!(target_arch = "aarch64") {
             Arch::ARM64
+        } else if cfg!(all(target_arch = "powerpc64", target_endian = "little")) {
+            Arch::PPC64LE
+        } else if cfg!(target_arch = "s390x") {
+            Arch::S390X
         } else {
             panic!("Unsupported architecture");
         };
 
         arch
     }
+
+    /// Detects the architecture of the host at runtime, falling back to the architecture yanvm was compiled for
+    ///
+    /// This differs from [`Arch::new`] when, for example, an x86 build of yanvm runs on an x64 host
+    pub fn detect() -> Self {
+        Self::detect_host().unwrap_or_else(Self::new)
+    }
+
+    #[cfg(windows)]
+    fn detect_host() -> Option<Self> {
+        // Set when a 32 bit process runs on a 64 bit host
+        let arch = std::env::var("PROCESSOR_ARCHITEW6432")
+            .or_else(|_| std::env::var("PROCESSOR_ARCHITECTURE"))
+            .ok()?;
+
+        Self::from_machine(&arch)
+    }
+
+    #[cfg(not(windows))]
+    fn detect_host() -> Option<Self> {
+        let output = std::process::Command::new("uname")
+            .arg("-m")
+            .output()
+            .ok()?;
+        let machine = String::from_utf8_lossy(&output.stdout);
+
+        let arch = Self::from_machine(machine.trim())?;
+
+        // Processes translated by Rosetta are told they run on x86_64
+        if cfg!(target_os = "macos") && arch == Arch::X64 {
+            let translated = std::process::Command::new("sysctl")
+                .args(["-n", "sysctl.proc_translated"])
+                .output()
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "1")
+                .unwrap_or(false);
+
+            if translated {
+                return Some(Arch::ARM64);
+            }
+        }
+
+        Some(arch)
+    }
+
+    /// Maps a machine name, as reported by `uname -m` or Windows, to an architecture
+    fn from_machine(machine: &str) -> Option<Self> {
+        match machine.to_ascii_lowercase().as_str() {
+            "x86_64" | "amd64" | "x64" => Some(Arch::X64),
+            "i386" | "i486" | "i586" | "i686" | "x86" => Some(Arch::X86),
+            "aarch64" | "arm64" => Some(Arch::ARM64),
+            // armv8l is a 64 bit cpu running in 32 bit mode
+            "armv8l" => Some(Arch::ARMv7),
+            machine if machine.starts_with("armv7") => Some(Arch::ARMv7),
+            machine if machine.starts_with("armv6") => Some(Arch::ARMv6),
+            "ppc64le" => Some(Arch::PPC64LE),
+            "s390x" => Some(Arch::S390X),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Arch {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "x86" => Ok(Arch::X86),
+            "x64" => Ok(Arch::X64),
+            "armv7l" => Ok(Arch::ARMv7),
+            "arm64" => Ok(Arch::ARM64),
+            "armv6l" => Ok(Arch::ARMv6),
+            "ppc64le" => Ok(Arch::PPC64LE),
+            "s390x" => Ok(Arch::S390X),
+            _ => Err(VersionError::UnknownArch(s.to_string())),
+        }
+    }
 }
 
 impl std::fmt::Display for Arch {
@@ -35,15 +184,93 @@ impl std::fmt::Display for Arch {
             Arch::X64 => write!(f, "x64"),
             Arch::ARMv7 => write!(f, "armv7l"),
             Arch::ARM64 => write!(f, "arm64"),
+            Arch::ARMv6 => write!(f, "armv6l"),
+            Arch::PPC64LE => write!(f, "ppc64le"),
+            Arch::S390X => write!(f, "s390x"),
         }
     }
 }
 
-#[derive(Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// A release channel of NodeJS, prereleases are published separately from stable releases
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Channel {
+    Release,
+    Nightly,
+    Rc,
+    V8Canary,
+}
+
+impl Channel {
+    const PRERELEASES: [Channel; 3] = [Channel::Nightly, Channel::Rc, Channel::V8Canary];
+
+    /// The name of the channel, as used in specs, prerelease tags and mirror urls
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Channel::Release => "release",
+            Channel::Nightly => "nightly",
+            Channel::Rc => "rc",
+            Channel::V8Canary => "v8-canary",
+        }
+    }
+
+    /// The mirror that publishes this channel, stable releases are published by the mirror of each libc
+    pub fn dist_url(&self, libc: Libc) -> String {
+        match self {
+            Channel::Release => libc.dist_url().to_string(),
+            channel => format!("{}/{}", crate::consts::DOWNLOAD_URL, channel.name()),
+        }
+    }
+
+    /// The channel a spec is resolved from, such as `nightly`, `rc/20` or `v21.0.0-rc.1`
+    pub fn from_spec(spec: &str) -> Self {
+        let name = spec.split('/').next().unwrap_or(spec);
+
+        Self::PRERELEASES
+            .into_iter()
+            .find(|channel| channel.name() == name)
+            .or_else(|| {
+                // Hyphen ranges such as `18 - 20`, and linked names such as `my-build`, are not prereleases
+                if range::VersionRange::is_range(spec) {
+                    return None;
+                }
+
+                let (version, pre) = spec.split_once('-')?;
+                let parts = version.strip_prefix('v').unwrap_or(version).split('.');
+
+                if parts.clone().count() != 3
+                    || parts.clone().any(|part| part.parse::<i32>().is_err())
+                {
+                    return None;
+                }
+
+                Some(Self::from_pre(pre))
+            })
+            .unwrap_or(Channel::Release)
+    }
+
+    /// The channel of a prerelease tag, such as `rc.1` or `nightly20230801d2d9cb6d2c`
+    fn from_pre(pre: &str) -> Self {
+        Self::PRERELEASES
+            .into_iter()
+            .find(|channel| pre.starts_with(channel.name()))
+            .unwrap_or(Channel::Nightly)
+    }
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Version {
     pub major: i32,
     pub minor: i32,
     pub patch: i32,
+    /// The prerelease tag, such as `rc.1`, which is only set for versions from prerelease channels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre: Option<String>,
 }
 
 impl Version {
@@ -52,8 +279,95 @@ impl Version {
             major,
             minor,
             patch,
+            pre: None,
         }
     }
+
+    pub fn channel(&self) -> Channel {
+        self.pre
+            .as_deref()
+            .map_or(Channel::Release, Channel::from_pre)
+    }
+
+    /// The minimum glibc version required by the official Linux build of this version, if known
+    pub fn required_glibc(&self) -> Option<(u32, u32)> {
+        crate::consts::GLIBC_REQUIREMENTS
+            .iter()
+            .find(|(major, _)| self.major >= *major)
+            .map(|(_, glibc)| *glibc)
+    }
+
+    /// Checks whether this version matches a spec
+    ///
+    /// Possibly partial versions, such as `18`, `v18.17` or `18.17.1`, only match stable releases.
+    /// Prereleases are matched by their channel, optionally followed by a partial version such as `rc/20`,
+    /// or by their full version such as `v21.0.0-rc.1`. Ranges such as `>=18 <21` are matched like npm does
+    pub fn matches(&self, spec: &str) -> bool {
+        if range::VersionRange::is_range(spec) {
+            return spec
+                .parse::<range::VersionRange>()
+                .is_ok_and(|range| range.matches(self));
+        }
+
+        if let Some(channel) = Channel::PRERELEASES
+            .into_iter()
+            .find(|channel| spec.split('/').next() == Some(channel.name()))
+        {
+            return self.channel() == channel
+                && spec
+                    .split_once('/')
+                    .is_none_or(|(_, partial)| self.matches_partial(partial));
+        }
+
+        if spec.contains('-') {
+            return spec.parse::<Version>().ok().as_ref() == Some(self);
+        }
+
+        self.pre.is_none() && self.matches_partial(spec)
+    }
+
+    fn matches_partial(&self, spec: &str) -> bool {
+        let spec = spec.strip_prefix('v').unwrap_or(spec);
+        let parts = spec.split('.').collect::<Vec<_>>();
+
+        !spec.is_empty()
+            && parts.len() <= 3
+            && parts
+                .iter()
+                .zip([self.major, self.minor, self.patch])
+                .all(|(part, number)| part.parse::<i32>().ok() == Some(number))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    /// Orders prereleases before the stable release of the same version, comparing numeric parts of their tags numerically
+    fn cmp(&self, other: &Self) -> Ordering {
+        let pre_key = |pre: &Option<String>| {
+            pre.as_ref().map(|pre| {
+                pre.split('.')
+                    .map(|part| match part.parse::<u64>() {
+                        Ok(number) => (number, String::new()),
+                        Err(_) => (0, part.to_string()),
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (pre_key(&self.pre), pre_key(&other.pre)) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(pre), Some(other)) => pre.cmp(&other),
+            })
+    }
 }
 
 impl From<(i32, i32, i32)> for Version {
@@ -68,8 +382,213 @@ impl From<Version> for (i32, i32, i32) {
     }
 }
 
+impl FromStr for Version {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VersionError::InvalidVersion(s.to_string());
+
+        let (numbers, pre) = match s.split_once('-') {
+            Some((numbers, pre)) if !pre.is_empty() => (numbers, Some(pre.to_string())),
+            Some(_) => return Err(invalid()),
+            None => (s, None),
+        };
+
+        let mut parts = numbers.strip_prefix('v').unwrap_or(numbers).split('.');
+        let mut next = || -> Result<i32, VersionError> {
+            parts
+                .next()
+                .and_then(|part| part.parse().ok())
+                .ok_or_else(invalid)
+        };
+
+        let mut version = Version::new(next()?, next()?, next()?);
+
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        version.pre = pre;
+
+        Ok(version)
+    }
+}
+
 impl std::fmt::Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        write!(f, "v{}.{}.{}", self.major, self.minor, self.patch)
+        write!(f, "v{}.{}.{}", self.major, self.minor, self.patch)?;
+
+        if let Some(pre) = &self.pre {
+            write!(f, "-{}", pre)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The C standard library a NodeJS build is linked against
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Libc {
+    #[default]
+    Glibc,
+    Musl,
+    /// Unofficial builds of newer releases that still run on glibc 2.17, such as on CentOS 7
+    #[serde(rename = "glibc-217")]
+    Glibc217,
+}
+
+impl Libc {
+    /// Detects whether the host uses musl, such as on Alpine Linux
+    pub fn detect() -> Self {
+        if cfg!(target_os = "linux") && Self::is_musl() {
+            Libc::Musl
+        } else {
+            Libc::Glibc
+        }
+    }
+
+    fn is_musl() -> bool {
+        let probe = |program: &str, arg: &str| {
+            std::process::Command::new(program)
+                .arg(arg)
+                .output()
+                .ok()
+                .map(|output| {
+                    // musl's ldd prints its version to stderr
+                    String::from_utf8_lossy(&output.stdout).to_string()
+                        + &String::from_utf8_lossy(&output.stderr)
+                })
+        };
+
+        // musl installs its dynamic loader as /lib/ld-musl-<arch>.so.1
+        let has_loader = std::fs::read_dir("/lib")
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .any(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-"))
+            })
+            .unwrap_or(false);
+
+        Self::is_musl_from(
+            probe("getconf", "GNU_LIBC_VERSION").as_deref(),
+            probe("ldd", "--version").as_deref(),
+            has_loader,
+        )
+    }
+
+    /// Decides whether the host uses musl, given the output of `getconf GNU_LIBC_VERSION` and `ldd --version`
+    ///
+    /// glibc is checked first, as glibc hosts may have musl's loader installed too, such as by Debian's musl package
+    fn is_musl_from(getconf: Option<&str>, ldd: Option<&str>, has_loader: bool) -> bool {
+        let glibc = getconf.is_some_and(|output| output.starts_with("glibc"))
+            || ldd.is_some_and(|output| output.contains("GLIBC") || output.contains("GNU libc"));
+
+        !glibc && (has_loader || ldd.is_some_and(|output| output.contains("musl")))
+    }
+
+    /// The version of glibc on the host, as reported by `getconf` or `ldd`
+    pub fn glibc_version() -> Option<(u32, u32)> {
+        let run = |program: &str, arg: &str| {
+            std::process::Command::new(program)
+                .arg(arg)
+                .output()
+                .ok()
+                .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        };
+
+        // `getconf` prints "glibc 2.17", while the first line of `ldd` ends with "2.17"
+        let output = run("getconf", "GNU_LIBC_VERSION")
+            .filter(|output| output.starts_with("glibc"))
+            .or_else(|| run("ldd", "--version"))?;
+
+        let version = output.lines().next()?.split_whitespace().last()?;
+        let (major, minor) = version.split_once('.')?;
+
+        Some((major.parse().ok()?, minor.parse().ok()?))
+    }
+
+    /// The suffix added to platform names of builds for this libc, such as `linux-x64-musl`
+    pub const fn suffix(&self) -> &'static str {
+        match self {
+            Libc::Glibc => "",
+            Libc::Musl => "-musl",
+            Libc::Glibc217 => "-glibc-217",
+        }
+    }
+
+    /// The mirror that publishes builds for this libc
+    pub const fn dist_url(&self) -> &'static str {
+        match self {
+            Libc::Glibc => crate::consts::DIST_URL,
+            Libc::Musl | Libc::Glibc217 => crate::consts::MUSL_DIST_URL,
+        }
+    }
+}
+
+impl std::fmt::Display for Libc {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Libc::Glibc => write!(f, "glibc"),
+            Libc::Musl => write!(f, "musl"),
+            Libc::Glibc217 => write!(f, "glibc-217"),
+        }
+    }
+}
+
+/// A version installed by yanvm, along with the platform it was built for
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InstalledVersion {
+    #[serde(flatten)]
+    pub version: Version,
+    // Versions installed before the architecture was recorded were always built for the compiled architecture
+    #[serde(default = "Arch::new")]
+    pub arch: Arch,
+    #[serde(default)]
+    pub libc: Libc,
+}
+
+impl InstalledVersion {
+    pub const fn new(version: Version, arch: Arch, libc: Libc) -> Self {
+        Self {
+            version,
+            arch,
+            libc,
+        }
+    }
+}
+
+impl std::fmt::Display for InstalledVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self.libc {
+            Libc::Glibc => write!(f, "{} ({})", self.version, self.arch),
+            _ => write!(f, "{} ({}, {})", self.version, self.arch, self.libc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_musl_from() {
+        let alpine_ldd = "musl libc (x86_64)\nVersion 1.2.4\nDynamic Program Loader\n";
+        let ubuntu_ldd = "ldd (Ubuntu GLIBC 2.35-0ubuntu3.1) 2.35\nCopyright (C) 2022 Free Software Foundation, Inc.\n";
+        let fedora_ldd = "ldd (GNU libc) 2.38\n";
+
+        assert!(Libc::is_musl_from(None, Some(alpine_ldd), true));
+        assert!(Libc::is_musl_from(None, None, true));
+        assert!(!Libc::is_musl_from(None, None, false));
+
+        // The musl package of Debian and Ubuntu installs its loader on glibc hosts
+        assert!(!Libc::is_musl_from(
+            Some("glibc 2.35\n"),
+            Some(ubuntu_ldd),
+            true
+        ));
+        assert!(!Libc::is_musl_from(None, Some(ubuntu_ldd), true));
+        assert!(!Libc::is_musl_from(None, Some(fedora_ldd), true));
+        assert!(!Libc::is_musl_from(Some("glibc 2.17\n"), None, true));
     }
 }
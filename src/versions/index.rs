@@ -1,9 +1,14 @@
 use serde::{Deserialize, Serialize};
 
-use crate::consts::CLIENT;
+use crate::{
+    consts::CLIENT,
+    versions::{Arch, Libc, Os, Version},
+};
 
+/// Parses the numeric part of a version, ignoring any prerelease tag
 pub fn parse_version(ver: impl AsRef<str>) -> (i32, i32, i32) {
-    let ver_number = ver.as_ref().replace('v', "");
+    let ver = ver.as_ref();
+    let ver_number = ver.split('-').next().unwrap_or(ver).replace('v', "");
     let mut ver = ver_number.split('.');
     let major = ver.next().unwrap().parse::<i32>().unwrap();
     let minor = ver.next().unwrap().parse::<i32>().unwrap();
@@ -13,20 +18,13 @@ pub fn parse_version(ver: impl AsRef<str>) -> (i32, i32, i32) {
 }
 
 fn sort_index(unsorted: &mut NodeIndex) {
-    unsorted.sort_by(|ver, old| {
-        let ver = parse_version(ver.version.replace('v', ""));
-        let ver_cmp = (ver.0 << 16) | (ver.1 << 8) | ver.2;
-
-        let old = parse_version(old.version.replace('v', ""));
-        let old_cmp = (old.0 << 16) | (old.1 << 8) | old.2;
-
-        ver_cmp.cmp(&old_cmp).reverse()
-    });
+    unsorted.sort_by_cached_key(|ver| std::cmp::Reverse(ver.version.parse::<Version>().ok()));
 }
 
-pub async fn list_index() -> reqwest::Result<NodeIndex> {
+/// Fetches the release index of the mirror at the given url
+pub async fn list_index(dist_url: &str) -> reqwest::Result<NodeIndex> {
     let index: NodeIndex = CLIENT
-        .get("https://nodejs.org/dist/index.json")
+        .get(format!("{}/index.json", dist_url))
         .send()
         .await?
         .json()
@@ -34,7 +32,7 @@ pub async fn list_index() -> reqwest::Result<NodeIndex> {
 
     let mut filtered: NodeIndex = index
         .iter()
-        .filter(|x| x.version.starts_with('v'))
+        .filter(|x| x.version.starts_with('v') && x.version.trim().parse::<Version>().is_ok())
         .map(|x| {
             let mut ver = x.clone();
             ver.version = x.version.replace('\n', "");
@@ -48,6 +46,30 @@ pub async fn list_index() -> reqwest::Result<NodeIndex> {
     Ok(filtered)
 }
 
+/// Fetches the checksums of every file published for a release, as pairs of sha256 hash and file name
+pub async fn list_shasums(dist_url: &str, version: &str) -> reqwest::Result<Vec<(String, String)>> {
+    let shasums = CLIENT
+        .get(format!("{}/{}/SHASUMS256.txt", dist_url, version))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(parse_shasums(&shasums))
+}
+
+fn parse_shasums(shasums: &str) -> Vec<(String, String)> {
+    shasums
+        .lines()
+        .filter_map(|line| {
+            let (hash, name) = line.split_once(char::is_whitespace)?;
+
+            Some((hash.to_string(), name.trim().to_string()))
+        })
+        .collect()
+}
+
 pub type NodeIndex = Vec<NodeIndexElement>;
 
 impl std::fmt::Display for NodeIndexElement {
@@ -92,6 +114,8 @@ pub enum File {
     Headers,
     #[serde(rename = "linux-arm64")]
     LinuxArm64,
+    #[serde(rename = "linux-arm64-musl")]
+    LinuxArm64Musl,
     #[serde(rename = "linux-armv6l")]
     LinuxArmv6L,
     #[serde(rename = "linux-armv7l")]
@@ -102,6 +126,10 @@ pub enum File {
     LinuxS390X,
     #[serde(rename = "linux-x64")]
     LinuxX64,
+    #[serde(rename = "linux-x64-musl")]
+    LinuxX64Musl,
+    #[serde(rename = "linux-x64-glibc-217")]
+    LinuxX64Glibc217,
     #[serde(rename = "linux-x86")]
     LinuxX86,
     #[serde(rename = "osx-arm64-tar")]
@@ -118,6 +146,10 @@ pub enum File {
     SunosX64,
     #[serde(rename = "sunos-x86")]
     SunosX86,
+    #[serde(rename = "win-arm64-7z")]
+    WinArm647Z,
+    #[serde(rename = "win-arm64-zip")]
+    WinArm64Zip,
     #[serde(rename = "win-x64-7z")]
     WinX647Z,
     #[serde(rename = "win-x64-exe")]
@@ -134,6 +166,34 @@ pub enum File {
     WinX86Msi,
     #[serde(rename = "win-x86-zip")]
     WinX86Zip,
+    /// Files not listed above, which mirrors such as unofficial-builds may publish
+    #[serde(other)]
+    Unknown,
+}
+
+impl std::fmt::Display for File {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(key)) => write!(f, "{}", key),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl File {
+    /// The index entry of the binary archive for the given platform, if one exists
+    pub fn for_platform(os: Os, arch: Arch, libc: Libc) -> Option<Self> {
+        let key = match os {
+            Os::Win => format!("win-{}-zip", arch),
+            Os::Darwin => format!("osx-{}-tar", arch),
+            Os::Linux => format!("linux-{}{}", arch, libc.suffix()),
+        };
+
+        match serde_json::from_value(serde_json::Value::String(key)).ok()? {
+            File::Unknown => None,
+            file => Some(file),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -145,6 +205,11 @@ pub enum LtsEnum {
     Erbium,
     Fermium,
     Gallium,
+    Hydrogen,
+    Iron,
+    Jod,
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -155,6 +220,8 @@ pub enum Zlib {
     The123,
     #[serde(rename = "1.2.8")]
     The128,
+    #[serde(other)]
+    Unknown,
 }
 
 #[cfg(test)]
@@ -163,7 +230,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_list_index() {
-        let index = list_index().await.unwrap();
+        let index = list_index(crate::consts::DIST_URL).await.unwrap();
         assert!(!index.is_empty());
     }
 
@@ -172,6 +239,76 @@ mod tests {
         assert_eq!(parse_version("v1.2.3"), (1, 2, 3));
 
         assert_eq!(parse_version("v16.15.1"), (16, 15, 1));
+
+        assert_eq!(parse_version("v21.0.0-rc.1"), (21, 0, 0));
+    }
+
+    #[test]
+    fn test_file_for_platform() {
+        assert_eq!(
+            File::for_platform(Os::Linux, Arch::S390X, Libc::Glibc),
+            Some(File::LinuxS390X)
+        );
+        assert_eq!(
+            File::for_platform(Os::Linux, Arch::ARMv6, Libc::Glibc),
+            Some(File::LinuxArmv6L)
+        );
+        assert_eq!(
+            File::for_platform(Os::Linux, Arch::X64, Libc::Musl),
+            Some(File::LinuxX64Musl)
+        );
+        assert_eq!(
+            File::for_platform(Os::Darwin, Arch::X64, Libc::Glibc),
+            Some(File::OsxX64Tar)
+        );
+        assert_eq!(
+            File::for_platform(Os::Win, Arch::X86, Libc::Glibc),
+            Some(File::WinX86Zip)
+        );
+        assert_eq!(
+            File::for_platform(Os::Win, Arch::ARM64, Libc::Glibc),
+            Some(File::WinArm64Zip)
+        );
+        assert_eq!(File::for_platform(Os::Win, Arch::S390X, Libc::Glibc), None);
+        assert_eq!(File::for_platform(Os::Linux, Arch::S390X, Libc::Musl), None);
+        assert_eq!(
+            File::for_platform(Os::Linux, Arch::X64, Libc::Glibc217),
+            Some(File::LinuxX64Glibc217)
+        );
+    }
+
+    #[test]
+    fn test_parse_shasums() {
+        let shasums =
+            "abc123  node-v1.2.3-linux-x64.tar.xz\ndef456  node-v1.2.3-linux-x64.tar.gz\n\n";
+
+        assert_eq!(
+            parse_shasums(shasums),
+            vec![
+                (
+                    "abc123".to_string(),
+                    "node-v1.2.3-linux-x64.tar.xz".to_string()
+                ),
+                (
+                    "def456".to_string(),
+                    "node-v1.2.3-linux-x64.tar.gz".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_display() {
+        assert_eq!(File::LinuxX64Musl.to_string(), "linux-x64-musl");
+        assert_eq!(File::WinX86Zip.to_string(), "win-x86-zip");
+    }
+
+    #[test]
+    fn test_unknown_index_fields() {
+        let files: Vec<File> =
+            serde_json::from_str(r#"["linux-x64-musl", "linux-riscv64"]"#).unwrap();
+
+        assert_eq!(files, vec![File::LinuxX64Musl, File::Unknown]);
     }
 
     #[test]
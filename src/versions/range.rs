@@ -0,0 +1,296 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use super::{Version, VersionError};
+
+/// A version range as written in the `engines` field of a package.json, such as `>=18 <21` or `^20.9 || 22`
+///
+/// Like npm, prereleases only match comparators naming a prerelease of the same version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionRange(Vec<Vec<Comparator>>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    version: Version,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+/// A possibly partial version, where missing or `x` parts match anything
+struct Partial {
+    major: Option<i32>,
+    minor: Option<i32>,
+    patch: Option<i32>,
+    pre: Option<String>,
+}
+
+impl VersionRange {
+    /// Whether the spec uses range syntax, rather than naming a version
+    pub fn is_range(spec: &str) -> bool {
+        spec.contains(|c: char| "<>=^~*| ".contains(c))
+            || spec.split('.').any(|part| part.eq_ignore_ascii_case("x"))
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        self.0.iter().any(|comparators| {
+            comparators
+                .iter()
+                .all(|comparator| comparator.matches(version))
+                && (version.pre.is_none()
+                    || comparators.iter().any(|comparator| {
+                        comparator.version.pre.is_some()
+                            && comparator.version.major == version.major
+                            && comparator.version.minor == version.minor
+                            && comparator.version.patch == version.patch
+                    }))
+        })
+    }
+}
+
+impl Comparator {
+    const fn new(op: Op, version: Version) -> Self {
+        Self { op, version }
+    }
+
+    /// A comparator that no version satisfies, such as `<0.0.0` or `>*`
+    const fn none() -> Self {
+        Self::new(Op::Lt, Version::new(0, 0, 0))
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let ordering = version.cmp(&self.version);
+
+        match self.op {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Gte => ordering != Ordering::Less,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Lte => ordering != Ordering::Greater,
+        }
+    }
+}
+
+impl Partial {
+    /// The lowest version matched, with missing parts as zero
+    fn floor(&self) -> Version {
+        Version {
+            pre: self.pre.clone(),
+            ..Version::new(
+                self.major.unwrap_or(0),
+                self.minor.unwrap_or(0),
+                self.patch.unwrap_or(0),
+            )
+        }
+    }
+
+    /// The version after every version matched, if any parts are missing
+    fn ceiling(&self) -> Option<Version> {
+        match (self.major, self.minor, self.patch) {
+            (Some(major), None, _) => Some(Version::new(major + 1, 0, 0)),
+            (Some(major), Some(minor), None) => Some(Version::new(major, minor + 1, 0)),
+            _ => None,
+        }
+    }
+
+    fn comparators(&self, op: &str) -> Result<Vec<Comparator>, VersionError> {
+        let floor = self.floor();
+        let ceiling = self.ceiling();
+
+        let comparators = match (op, self.major) {
+            ("" | "=" | ">=" | "<=" | "~" | "^", None) => vec![],
+            (">" | "<", None) => vec![Comparator::none()],
+            ("" | "=", Some(_)) => match ceiling {
+                Some(ceiling) => vec![
+                    Comparator::new(Op::Gte, floor),
+                    Comparator::new(Op::Lt, ceiling),
+                ],
+                None => vec![Comparator::new(Op::Eq, floor)],
+            },
+            (">", Some(_)) => match ceiling {
+                Some(ceiling) => vec![Comparator::new(Op::Gte, ceiling)],
+                None => vec![Comparator::new(Op::Gt, floor)],
+            },
+            (">=", Some(_)) => vec![Comparator::new(Op::Gte, floor)],
+            ("<", Some(_)) => vec![Comparator::new(Op::Lt, floor)],
+            ("<=", Some(_)) => match ceiling {
+                Some(ceiling) => vec![Comparator::new(Op::Lt, ceiling)],
+                None => vec![Comparator::new(Op::Lte, floor)],
+            },
+            ("~", Some(major)) => {
+                let ceiling = match self.minor {
+                    Some(minor) => Version::new(major, minor + 1, 0),
+                    None => Version::new(major + 1, 0, 0),
+                };
+
+                vec![
+                    Comparator::new(Op::Gte, floor),
+                    Comparator::new(Op::Lt, ceiling),
+                ]
+            }
+            ("^", Some(major)) => {
+                // The first non-zero part may not change
+                let ceiling = match (major, self.minor, self.patch) {
+                    (0, Some(0), Some(patch)) => Version::new(0, 0, patch + 1),
+                    (0, Some(minor), _) => Version::new(0, minor + 1, 0),
+                    _ => Version::new(major + 1, 0, 0),
+                };
+
+                vec![
+                    Comparator::new(Op::Gte, floor),
+                    Comparator::new(Op::Lt, ceiling),
+                ]
+            }
+            (op, _) => return Err(VersionError::InvalidRange(op.to_string())),
+        };
+
+        Ok(comparators)
+    }
+}
+
+impl FromStr for Partial {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VersionError::InvalidRange(s.to_string());
+
+        let s = s.strip_prefix('v').unwrap_or(s);
+        let (numbers, pre) = match s.split_once('-') {
+            Some((numbers, pre)) if !pre.is_empty() => (numbers, Some(pre.to_string())),
+            Some(_) => return Err(invalid()),
+            None => (s, None),
+        };
+
+        let mut parts = numbers
+            .split('.')
+            .map(|part| match part {
+                "x" | "X" | "*" => Ok(None),
+                part => part.parse().map(Some).map_err(|_| invalid()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if parts.len() > 3 {
+            return Err(invalid());
+        }
+
+        parts.resize(3, None);
+
+        let partial = Partial {
+            major: parts[0],
+            minor: parts[0].and(parts[1]),
+            patch: parts[0].and(parts[1]).and(parts[2]),
+            pre,
+        };
+
+        // Prerelease tags only apply to full versions
+        if partial.pre.is_some() && partial.patch.is_none() {
+            return Err(invalid());
+        }
+
+        Ok(partial)
+    }
+}
+
+impl FromStr for VersionRange {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split("||")
+            .map(|set| {
+                let mut tokens = Vec::<String>::new();
+
+                // Operators may be separated from their version by spaces, such as `>= 18`
+                for token in set.split_whitespace() {
+                    match tokens.last_mut() {
+                        Some(last) if last.chars().all(|c| "<>=^~".contains(c)) => {
+                            last.push_str(token)
+                        }
+                        _ => tokens.push(token.to_string()),
+                    }
+                }
+
+                match tokens.as_slice() {
+                    [from, hyphen, to] if hyphen == "-" => {
+                        let from = from.parse::<Partial>()?;
+                        let to = to.parse::<Partial>()?;
+
+                        let mut comparators = from.comparators(">=")?;
+                        comparators.extend(to.comparators("<=")?);
+
+                        Ok(comparators)
+                    }
+                    tokens => tokens
+                        .iter()
+                        .map(|token| {
+                            let split = token
+                                .find(|c: char| !"<>=^~".contains(c))
+                                .unwrap_or(token.len());
+                            let (op, partial) = token.split_at(split);
+
+                            partial.parse::<Partial>()?.comparators(op)
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                        .map(|comparators| comparators.concat()),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(VersionRange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(range: &str, version: &str) -> bool {
+        range
+            .parse::<VersionRange>()
+            .unwrap()
+            .matches(&version.parse().unwrap())
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(">=18", "v20.1.0"));
+        assert!(!matches(">=18", "v16.20.2"));
+        assert!(matches(">= 18.17 < 21", "v20.9.0"));
+        assert!(!matches(">=18.17 <21", "v21.0.0"));
+        assert!(!matches(">=18.17 <21", "v18.16.1"));
+        assert!(matches("^20.9", "v20.11.0"));
+        assert!(!matches("^20.9", "v20.8.0"));
+        assert!(matches("~18.17", "v18.17.1"));
+        assert!(!matches("~18.17", "v18.18.0"));
+        assert!(matches("18.x", "v18.0.0"));
+        assert!(matches("16 || 18", "v18.2.0"));
+        assert!(!matches("16 || 18", "v20.0.0"));
+        assert!(matches("18 - 20", "v20.11.0"));
+        assert!(!matches("18 - 20.1", "v20.2.0"));
+        assert!(matches("*", "v21.0.0"));
+        assert!(!matches(">20", "v20.11.0"));
+        assert!(matches("<=20", "v20.11.0"));
+        assert!(matches("^0.10.1", "v0.10.48"));
+        assert!(!matches("^0.10.1", "v0.12.0"));
+
+        // Prereleases only match when named explicitly
+        assert!(!matches(">=20", "v21.0.0-rc.1"));
+        assert!(matches(">=21.0.0-rc.0", "v21.0.0-rc.1"));
+    }
+
+    #[test]
+    fn test_is_range() {
+        assert!(VersionRange::is_range(">=18"));
+        assert!(VersionRange::is_range("18.x"));
+        assert!(VersionRange::is_range("^20"));
+        assert!(!VersionRange::is_range("v18.17.1"));
+        assert!(!VersionRange::is_range("rc/21"));
+        assert!(!VersionRange::is_range("my-build"));
+
+        assert!(">=18 <x.1.y".parse::<VersionRange>().is_err());
+    }
+}
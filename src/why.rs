@@ -0,0 +1,318 @@
+use std::path::PathBuf;
+
+use crate::{
+    config::{Config, ConfigError, Current},
+    project::{self, ProjectVersion},
+    session::Session,
+    shim,
+    system::SystemNode,
+    versions::Arch,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WhyError {
+    #[error("{0}")]
+    Config(#[from] ConfigError),
+    #[error("Failed to interact with IO: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A place the version can be chosen from, in order of priority
+///
+/// Not every place applies everywhere: shims never consult the shell session, and only shims read the environment variable
+#[derive(Debug, PartialEq, Eq)]
+pub enum Source {
+    Argument,
+    Env,
+    Session,
+    File,
+    Default,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Argument => write!(f, "Command line argument"),
+            Source::Env => write!(f, "{} (shims only)", shim::VERSION_VAR),
+            Source::Session => write!(f, "Shell session (not shims)"),
+            Source::File => write!(f, "Project version file"),
+            Source::Default => write!(f, "Default (use)"),
+        }
+    }
+}
+
+/// What a source set, either a spec to resolve, a project's pin, or an already resolved version
+pub enum Found {
+    Spec(String),
+    Project(ProjectVersion),
+    Version(Current),
+}
+
+impl std::fmt::Display for Found {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Found::Spec(spec) => write!(f, "{}", spec),
+            Found::Project(project) => {
+                match project.fallback {
+                    Some(i) => write!(
+                        f,
+                        "{} not installed, fell back to {}",
+                        project.written, project.fallbacks[i]
+                    )?,
+                    None if project.written != project.spec => {
+                        write!(f, "{} -> {}", project.written, project.spec)?
+                    }
+                    None => write!(f, "{}", project.spec)?,
+                }
+
+                write!(f, " from {}", project.path.display())
+            }
+            Found::Version(version) => write!(f, "{}", version),
+        }
+    }
+}
+
+pub struct Step {
+    pub source: Source,
+    pub found: Option<Found>,
+}
+
+/// The version the chosen spec resolved to
+pub enum Resolution {
+    Installed { version: Current, bin_path: PathBuf },
+    System(Option<SystemNode>),
+    NotInstalled(String),
+    Unset,
+}
+
+/// Every source the version could be chosen from, and what the first one that is set resolved to
+pub struct Trace {
+    pub steps: Vec<Step>,
+    /// The index of the step the version was chosen by
+    pub chosen: Option<usize>,
+    /// How the chosen spec was expanded, when it is an alias
+    pub alias: Option<String>,
+    pub resolution: Resolution,
+}
+
+/// Traces how the version is chosen in the working directory, given an optional version on the command line
+pub fn trace(
+    config: &Config,
+    version_str: Option<String>,
+    arch: Option<Arch>,
+) -> Result<Trace, WhyError> {
+    let steps = steps(
+        version_str,
+        std::env::var(shim::VERSION_VAR).ok(),
+        Session::from_env()
+            .ok()
+            .and_then(|session| session.linked_version(config)),
+        project::find_current(config)?,
+        config.current.clone(),
+    );
+
+    let chosen = steps.iter().position(|step| step.found.is_some());
+
+    let (alias, resolution) = match chosen.and_then(|i| steps[i].found.as_ref()) {
+        None => (None, Resolution::Unset),
+        Some(Found::Spec(spec) | Found::Project(ProjectVersion { spec, .. }))
+            if spec == "system" =>
+        {
+            (None, Resolution::System(SystemNode::find(config)))
+        }
+        Some(Found::Spec(spec) | Found::Project(ProjectVersion { spec, .. })) => {
+            let alias = match spec.as_str() {
+                "lts" => Some("lts picks the newest installed even major version".to_string()),
+                "latest" => Some("latest picks the newest installed stable version".to_string()),
+                spec => linked_alias(config, spec),
+            };
+
+            let resolution = match config.resolve(spec, arch) {
+                Some(version) => Resolution::Installed {
+                    bin_path: config.bin_path(&version)?,
+                    version,
+                },
+                None => Resolution::NotInstalled(spec.clone()),
+            };
+
+            (alias, resolution)
+        }
+        Some(Found::Version(version)) => {
+            let alias = match version {
+                Current::Linked(name) => linked_alias(config, name),
+                Current::Installed(_) => None,
+            };
+
+            let resolution = Resolution::Installed {
+                bin_path: config.bin_path(version)?,
+                version: version.clone(),
+            };
+
+            (alias, resolution)
+        }
+    };
+
+    Ok(Trace {
+        steps,
+        chosen,
+        alias,
+        resolution,
+    })
+}
+
+/// The steps in order of priority, with what each source set
+fn steps(
+    argument: Option<String>,
+    env: Option<String>,
+    session: Option<Current>,
+    project: Option<ProjectVersion>,
+    current: Option<Current>,
+) -> Vec<Step> {
+    vec![
+        Step {
+            source: Source::Argument,
+            found: argument.map(Found::Spec),
+        },
+        Step {
+            source: Source::Env,
+            found: env.map(Found::Spec),
+        },
+        Step {
+            source: Source::Session,
+            found: session.map(Found::Version),
+        },
+        Step {
+            source: Source::File,
+            found: project.map(Found::Project),
+        },
+        Step {
+            source: Source::Default,
+            found: current.map(Found::Version),
+        },
+    ]
+}
+
+fn linked_alias(config: &Config, name: &str) -> Option<String> {
+    config
+        .links
+        .iter()
+        .find(|link| link.name == name)
+        .map(|link| format!("{} is linked to {}", link.name, link.path.display()))
+}
+
+impl std::fmt::Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            let found = match &step.found {
+                Some(found) => found.to_string(),
+                None => "not set".to_string(),
+            };
+
+            let marker = if Some(i) == self.chosen {
+                "  <- used"
+            } else {
+                ""
+            };
+
+            writeln!(
+                f,
+                "{}. {:<28} {}{}",
+                i + 1,
+                step.source.to_string(),
+                found,
+                marker
+            )?;
+        }
+
+        if let Some(alias) = &self.alias {
+            writeln!(f, "   Alias: {}", alias)?;
+        }
+
+        match &self.resolution {
+            Resolution::Installed { version, bin_path } => {
+                write!(f, "Resolved to {} in {}", version, bin_path.display())
+            }
+            Resolution::System(Some(system)) => write!(f, "Resolved to {}", system),
+            Resolution::System(None) => {
+                write!(
+                    f,
+                    "Resolved to system, but no system NodeJS installation was found"
+                )
+            }
+            Resolution::NotInstalled(spec) => write!(
+                f,
+                "{} is not installed. Run `yanvm install {}` to install it",
+                spec, spec
+            ),
+            Resolution::Unset => write!(
+                f,
+                "No version is set, so the system NodeJS installation is used"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::versions::{InstalledVersion, Libc, Version};
+
+    fn project(written: &str, spec: &str, fallback: Option<usize>) -> ProjectVersion {
+        ProjectVersion {
+            spec: spec.to_string(),
+            written: written.to_string(),
+            fallbacks: vec!["20.9.0".to_string()],
+            fallback,
+            path: PathBuf::from("/project/.nvmrc"),
+        }
+    }
+
+    #[test]
+    fn test_steps() {
+        let session = Current::Installed(InstalledVersion::new(
+            Version::new(18, 17, 1),
+            Arch::X64,
+            Libc::Glibc,
+        ));
+
+        let chain = steps(
+            None,
+            None,
+            Some(session),
+            Some(project("20", "20", None)),
+            Some(Current::Linked("custom".to_string())),
+        );
+
+        let sources = chain.iter().map(|step| &step.source).collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            vec![
+                &Source::Argument,
+                &Source::Env,
+                &Source::Session,
+                &Source::File,
+                &Source::Default
+            ]
+        );
+        assert_eq!(chain.iter().position(|step| step.found.is_some()), Some(2));
+
+        let chain = steps(None, Some("16".to_string()), None, None, None);
+        assert_eq!(chain.iter().position(|step| step.found.is_some()), Some(1));
+    }
+
+    #[test]
+    fn test_found_project() {
+        assert_eq!(
+            Found::Project(project("lts/hydrogen", "18", None)).to_string(),
+            "lts/hydrogen -> 18 from /project/.nvmrc"
+        );
+        assert_eq!(
+            Found::Project(project("22.1.0", "20.9.0", Some(0))).to_string(),
+            "22.1.0 not installed, fell back to 20.9.0 from /project/.nvmrc"
+        );
+        assert_eq!(
+            Found::Project(project("20", "20", None)).to_string(),
+            "20 from /project/.nvmrc"
+        );
+    }
+}